@@ -0,0 +1,10 @@
+use cosmwasm_schema::write_api;
+
+use multicall_executor::msg::{ExecuteMsg, InstantiateMsg};
+
+fn main() {
+    write_api! {
+        instantiate: InstantiateMsg,
+        execute: ExecuteMsg,
+    }
+}
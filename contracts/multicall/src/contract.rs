@@ -0,0 +1,181 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{BankMsg, DepsMut, Env, MessageInfo, Reply, Response, StdResult, SubMsg};
+use multicall::executor::{build_next_call_msg, store_calls};
+
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, MsgReplyId};
+use crate::state::{load_refund_state, remove_refund_state, store_refund_state, RefundState};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Multicall { calls } => {
+            store_calls(deps.storage, calls)?;
+            store_refund_state(
+                deps.storage,
+                &RefundState {
+                    recipient: info.sender,
+                },
+            )?;
+            // execute first call and initiate callback loop
+            handle_call_reply(deps, &env)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match MsgReplyId::from_repr(reply.id) {
+        Some(MsgReplyId::Call) => handle_call_reply(deps, &env),
+        None => Err(ContractError::InvalidReplyId {}),
+    }
+}
+
+fn handle_call_reply(mut deps: DepsMut, env: &Env) -> Result<Response, ContractError> {
+    let Some(call_msg) = build_next_call_msg(deps.branch(), env)? else {
+        return Ok(Response::new().add_messages(refund_leftover_msg(deps, env)?));
+    };
+
+    Ok(Response::new().add_submessage(SubMsg::reply_on_success(call_msg, MsgReplyId::Call.repr())))
+}
+
+/// Sends the whole contract balance back to the sender, anyone could take it with a `bank` call
+/// otherwise. This includes denoms received from the calls, like the output of a `stargate` swap,
+/// which can't be known in advance.
+fn refund_leftover_msg(deps: DepsMut, env: &Env) -> StdResult<Option<BankMsg>> {
+    let refund_state = load_refund_state(deps.storage)?;
+    remove_refund_state(deps.storage);
+
+    let leftover: Vec<_> = deps
+        .querier
+        .query_all_balances(&env.contract.address)?
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .collect();
+
+    if leftover.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(BankMsg::Send {
+        to_address: refund_state.recipient.to_string(),
+        amount: leftover,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies_with_balance, mock_env, mock_info},
+        Coin, CosmosMsg, SubMsgResponse, SubMsgResult,
+    };
+    use multicall::Call;
+
+    use super::*;
+
+    fn bank_call(to_address: &str) -> Call {
+        Call::Bank {
+            to_address: to_address.to_owned(),
+            amount: vec![Coin::new(10, "uosmo")],
+        }
+    }
+
+    fn call_reply() -> Reply {
+        Reply {
+            id: MsgReplyId::Call.repr(),
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        }
+    }
+
+    fn dispatched_call(response: &Response) -> CosmosMsg {
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].id, MsgReplyId::Call.repr());
+        response.messages[0].msg.clone()
+    }
+
+    #[test]
+    fn dispatches_calls_in_order_from_the_replies() {
+        let mut deps = mock_dependencies_with_balance(&[]);
+        let calls = vec![bank_call("first"), bank_call("second"), bank_call("third")];
+
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &[]),
+            ExecuteMsg::Multicall {
+                calls: calls.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            dispatched_call(&response),
+            calls[0].clone().into_cosmos_msg().unwrap()
+        );
+
+        for call in &calls[1..] {
+            let response = reply(deps.as_mut(), mock_env(), call_reply()).unwrap();
+            assert_eq!(
+                dispatched_call(&response),
+                call.clone().into_cosmos_msg().unwrap()
+            );
+        }
+
+        // nothing is left to refund after the last call
+        let response = reply(deps.as_mut(), mock_env(), call_reply()).unwrap();
+        assert!(response.messages.is_empty());
+
+        // the state of the finished multicall is gone
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &[]),
+            ExecuteMsg::Multicall { calls },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn refunds_whole_balance_after_last_call() {
+        // uatom was received from a call, only uosmo was sent with the multicall
+        let mut deps =
+            mock_dependencies_with_balance(&[Coin::new(5, "uosmo"), Coin::new(20, "uatom")]);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &[Coin::new(100, "uosmo")]),
+            ExecuteMsg::Multicall {
+                calls: vec![bank_call("receiver")],
+            },
+        )
+        .unwrap();
+        let response = reply(deps.as_mut(), mock_env(), call_reply()).unwrap();
+
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "sender".to_owned(),
+                amount: vec![Coin::new(5, "uosmo"), Coin::new(20, "uatom")],
+            })
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use cosmwasm_std::StdError;
+use multicall::error::MulticallError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    MulticallError(#[from] MulticallError),
+
+    #[error("Invalid reply id")]
+    InvalidReplyId {},
+}
@@ -0,0 +1,17 @@
+use enum_repr::EnumRepr;
+
+use cosmwasm_schema::cw_serde;
+use multicall::Call;
+
+#[cw_serde]
+pub struct InstantiateMsg {}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    Multicall { calls: Vec<Call> },
+}
+
+#[EnumRepr(type = "u64")]
+pub enum MsgReplyId {
+    Call = 1,
+}
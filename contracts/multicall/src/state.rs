@@ -0,0 +1,23 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdResult, Storage};
+use cw_storage_plus::Item;
+
+const REFUND_STATE: Item<RefundState> = Item::new("refund_state");
+
+/// Where to send what is left in the contract after the last call.
+#[cw_serde]
+pub struct RefundState {
+    pub recipient: Addr,
+}
+
+pub fn store_refund_state(storage: &mut dyn Storage, data: &RefundState) -> StdResult<()> {
+    REFUND_STATE.save(storage, data)
+}
+
+pub fn load_refund_state(storage: &dyn Storage) -> StdResult<RefundState> {
+    REFUND_STATE.load(storage)
+}
+
+pub fn remove_refund_state(storage: &mut dyn Storage) {
+    REFUND_STATE.remove(storage);
+}
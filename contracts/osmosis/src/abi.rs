@@ -0,0 +1,389 @@
+use cosmwasm_std::Decimal;
+use osmosis_router::{OsmosisSwapMsg, TwapSlippage};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+
+use crate::{msg::AfterSwapAction, ContractError};
+
+const WORD_SIZE: usize = 32;
+
+const ACTION_BANK_SEND: u8 = 0;
+const ACTION_IBC_TRANSFER: u8 = 1;
+
+/// Swap request sent by EVM contracts through Axelar GMP.
+pub struct GmpSwapRequest {
+    pub swap_msg: OsmosisSwapMsg,
+    pub after_swap_action: AfterSwapAction,
+    pub local_fallback_address: String,
+}
+
+/// Decodes a payload created with
+/// `abi.encode(uint64[] poolIds, string[] tokenOutDenoms, uint256 tokenOutMinAmount,
+/// uint16 slippageBps, uint8 actionType, string receiver, string channel,
+/// string localFallbackAddress)`.
+///
+/// Exactly one of `tokenOutMinAmount` and `slippageBps` must be non-zero. `actionType` is 0 for
+/// a bank send and 1 for an ibc transfer over `channel`, which is ignored for bank sends.
+pub fn decode_gmp_swap_request(payload: &[u8]) -> Result<GmpSwapRequest, ContractError> {
+    let decoder = AbiDecoder::new(payload);
+
+    let pool_ids = decoder.uint64_array(0)?;
+    let token_out_denoms = decoder.string_array(WORD_SIZE)?;
+    if pool_ids.len() != token_out_denoms.len() {
+        return Err(invalid_payload(
+            "pool ids and token out denoms differ in length",
+        ));
+    }
+
+    let token_out_min_amount = decoder.uint(2 * WORD_SIZE)?;
+    let slippage_bps = u16::try_from(decoder.uint(3 * WORD_SIZE)?)
+        .map_err(|_e| invalid_payload("slippage out of range"))?;
+    let action_type = u8::try_from(decoder.uint(4 * WORD_SIZE)?)
+        .map_err(|_e| invalid_payload("action type out of range"))?;
+    let receiver = decoder.string(5 * WORD_SIZE)?;
+    let channel = decoder.string(6 * WORD_SIZE)?;
+    let local_fallback_address = decoder.string(7 * WORD_SIZE)?;
+
+    let path = pool_ids
+        .into_iter()
+        .zip(token_out_denoms)
+        .map(|(pool_id, token_out_denom)| SwapAmountInRoute {
+            pool_id,
+            token_out_denom,
+        })
+        .collect();
+
+    let after_swap_action = match action_type {
+        ACTION_BANK_SEND => AfterSwapAction::BankSend { receiver },
+        ACTION_IBC_TRANSFER => AfterSwapAction::IbcTransfer {
+            receiver,
+            channel,
+            next_memo: None,
+            timeout_timestamp: None,
+            timeout_height: None,
+            forward_hops: vec![],
+        },
+        _ => return Err(invalid_payload("unknown action type")),
+    };
+
+    Ok(GmpSwapRequest {
+        swap_msg: OsmosisSwapMsg {
+            // zero means not set
+            token_out_min_amount: (token_out_min_amount != 0)
+                .then(|| token_out_min_amount.to_string()),
+            path,
+            split_routes: vec![],
+            twap_slippage: (slippage_bps != 0).then(|| TwapSlippage {
+                slippage: Decimal::from_ratio(slippage_bps, 100u128),
+                twap: None,
+            }),
+        },
+        after_swap_action,
+        local_fallback_address,
+    })
+}
+
+/// Minimal decoder for the Solidity ABI encoding of the types used in GMP payloads. Offsets of
+/// dynamic types are relative to the start of the enclosing tuple or array.
+struct AbiDecoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> AbiDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn word(&self, offset: usize) -> Result<&'a [u8], ContractError> {
+        let end = offset
+            .checked_add(WORD_SIZE)
+            .ok_or_else(|| invalid_payload("offset overflow"))?;
+
+        self.data
+            .get(offset..end)
+            .ok_or_else(|| invalid_payload("unexpected end of payload"))
+    }
+
+    /// Reads a uint, values above `u128::MAX` are rejected.
+    fn uint(&self, offset: usize) -> Result<u128, ContractError> {
+        let word = self.word(offset)?;
+        let (high, low) = word.split_at(WORD_SIZE / 2);
+        if high.iter().any(|byte| *byte != 0) {
+            return Err(invalid_payload("uint out of range"));
+        }
+
+        Ok(u128::from_be_bytes(low.try_into().unwrap()))
+    }
+
+    fn usize(&self, offset: usize) -> Result<usize, ContractError> {
+        usize::try_from(self.uint(offset)?).map_err(|_e| invalid_payload("length out of range"))
+    }
+
+    /// Decoder for the dynamic value referenced at `offset`.
+    fn tail(&self, offset: usize) -> Result<AbiDecoder<'a>, ContractError> {
+        let start = self.usize(offset)?;
+        let data = self
+            .data
+            .get(start..)
+            .ok_or_else(|| invalid_payload("offset out of bounds"))?;
+
+        Ok(AbiDecoder::new(data))
+    }
+
+    fn string(&self, offset: usize) -> Result<String, ContractError> {
+        let tail = self.tail(offset)?;
+        let len = tail.usize(0)?;
+        let bytes = tail
+            .data
+            .get(WORD_SIZE..)
+            .and_then(|data| data.get(..len))
+            .ok_or_else(|| invalid_payload("unexpected end of payload"))?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_e| invalid_payload("invalid utf-8 string"))
+    }
+
+    /// Length of an array with one head word per element, checked against the remaining payload.
+    fn array_len(&self) -> Result<usize, ContractError> {
+        let len = self.usize(0)?;
+        if len > (self.data.len() - WORD_SIZE) / WORD_SIZE {
+            return Err(invalid_payload("array length out of bounds"));
+        }
+
+        Ok(len)
+    }
+
+    fn uint64_array(&self, offset: usize) -> Result<Vec<u64>, ContractError> {
+        let tail = self.tail(offset)?;
+        let len = tail.array_len()?;
+
+        (0..len)
+            .map(|i| {
+                let value = tail.uint(WORD_SIZE + i * WORD_SIZE)?;
+                u64::try_from(value).map_err(|_e| invalid_payload("uint64 out of range"))
+            })
+            .collect()
+    }
+
+    fn string_array(&self, offset: usize) -> Result<Vec<String>, ContractError> {
+        let tail = self.tail(offset)?;
+        let len = tail.array_len()?;
+        let elements = AbiDecoder::new(&tail.data[WORD_SIZE..]);
+
+        (0..len).map(|i| elements.string(i * WORD_SIZE)).collect()
+    }
+}
+
+fn invalid_payload(msg: &str) -> ContractError {
+    ContractError::InvalidAbiPayload {
+        msg: msg.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `abi.encode([uint64(1), 678], ["uatom", "uosmo"], uint256(1000), uint16(0), uint8(1),
+    /// "cosmos1receiver", "channel-0", "osmo1fallback")`, one word per line.
+    const IBC_TRANSFER_PAYLOAD: &[&str] = &[
+        "0000000000000000000000000000000000000000000000000000000000000100",
+        "0000000000000000000000000000000000000000000000000000000000000160",
+        "00000000000000000000000000000000000000000000000000000000000003e8",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000240",
+        "0000000000000000000000000000000000000000000000000000000000000280",
+        "00000000000000000000000000000000000000000000000000000000000002c0",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "00000000000000000000000000000000000000000000000000000000000002a6",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000000000000000000000000000000000000000000000000000000000040",
+        "0000000000000000000000000000000000000000000000000000000000000080",
+        "0000000000000000000000000000000000000000000000000000000000000005",
+        "7561746f6d000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000005",
+        "756f736d6f000000000000000000000000000000000000000000000000000000",
+        "000000000000000000000000000000000000000000000000000000000000000f",
+        "636f736d6f733172656365697665720000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000009",
+        "6368616e6e656c2d300000000000000000000000000000000000000000000000",
+        "000000000000000000000000000000000000000000000000000000000000000d",
+        "6f736d6f3166616c6c6261636b00000000000000000000000000000000000000",
+    ];
+
+    /// `abi.encode([uint64(1)], ["uosmo"], uint256(0), uint16(150), uint8(0), "osmo1receiver", "",
+    /// "osmo1fallback")`, one word per line.
+    const BANK_SEND_PAYLOAD: &[&str] = &[
+        "0000000000000000000000000000000000000000000000000000000000000100",
+        "0000000000000000000000000000000000000000000000000000000000000140",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000096",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "00000000000000000000000000000000000000000000000000000000000001c0",
+        "0000000000000000000000000000000000000000000000000000000000000200",
+        "0000000000000000000000000000000000000000000000000000000000000220",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000020",
+        "0000000000000000000000000000000000000000000000000000000000000005",
+        "756f736d6f000000000000000000000000000000000000000000000000000000",
+        "000000000000000000000000000000000000000000000000000000000000000d",
+        "6f736d6f31726563656976657200000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "000000000000000000000000000000000000000000000000000000000000000d",
+        "6f736d6f3166616c6c6261636b00000000000000000000000000000000000000",
+    ];
+
+    fn payload(words: &[&str]) -> Vec<u8> {
+        let hex = words.concat();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn set_word(payload: &mut [u8], offset: usize, value: u128) {
+        payload[offset..offset + WORD_SIZE].fill(0);
+        payload[offset + WORD_SIZE / 2..offset + WORD_SIZE].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn decode_err(payload: &[u8]) -> String {
+        match decode_gmp_swap_request(payload) {
+            Err(ContractError::InvalidAbiPayload { msg }) => msg,
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("payload decoded"),
+        }
+    }
+
+    #[test]
+    fn decodes_ibc_transfer_request() {
+        let request = decode_gmp_swap_request(&payload(IBC_TRANSFER_PAYLOAD)).unwrap();
+
+        assert_eq!(
+            request.swap_msg,
+            OsmosisSwapMsg {
+                token_out_min_amount: Some("1000".to_owned()),
+                path: vec![
+                    SwapAmountInRoute {
+                        pool_id: 1,
+                        token_out_denom: "uatom".to_owned(),
+                    },
+                    SwapAmountInRoute {
+                        pool_id: 678,
+                        token_out_denom: "uosmo".to_owned(),
+                    },
+                ],
+                split_routes: vec![],
+                twap_slippage: None,
+            }
+        );
+        assert_eq!(
+            request.after_swap_action,
+            AfterSwapAction::IbcTransfer {
+                receiver: "cosmos1receiver".to_owned(),
+                channel: "channel-0".to_owned(),
+                next_memo: None,
+                timeout_timestamp: None,
+                timeout_height: None,
+                forward_hops: vec![],
+            }
+        );
+        assert_eq!(request.local_fallback_address, "osmo1fallback");
+    }
+
+    #[test]
+    fn decodes_bank_send_request_with_slippage() {
+        let request = decode_gmp_swap_request(&payload(BANK_SEND_PAYLOAD)).unwrap();
+
+        assert_eq!(request.swap_msg.token_out_min_amount, None);
+        assert_eq!(
+            request.swap_msg.twap_slippage,
+            Some(TwapSlippage {
+                slippage: Decimal::from_ratio(150u128, 100u128),
+                twap: None,
+            })
+        );
+        assert_eq!(
+            request.after_swap_action,
+            AfterSwapAction::BankSend {
+                receiver: "osmo1receiver".to_owned(),
+            }
+        );
+        assert_eq!(request.local_fallback_address, "osmo1fallback");
+    }
+
+    #[test]
+    fn rejects_truncated_payload() {
+        let payload = payload(IBC_TRANSFER_PAYLOAD);
+
+        // the fallback address bytes are cut off
+        assert_eq!(
+            decode_err(&payload[..payload.len() - WORD_SIZE]),
+            "unexpected end of payload"
+        );
+        // the head is incomplete and the pool ids offset points past the end
+        assert_eq!(
+            decode_err(&payload[..3 * WORD_SIZE]),
+            "offset out of bounds"
+        );
+        assert_eq!(decode_err(&[]), "unexpected end of payload");
+    }
+
+    #[test]
+    fn rejects_out_of_range_offsets() {
+        let mut payload = payload(IBC_TRANSFER_PAYLOAD);
+        let len = payload.len() as u128;
+
+        // pool ids offset past the end of the payload
+        set_word(&mut payload, 0, len + 1);
+        assert_eq!(decode_err(&payload), "offset out of bounds");
+
+        // pool ids offset pointing at the last bytes, the length word is cut off
+        set_word(&mut payload, 0, len - 1);
+        assert_eq!(decode_err(&payload), "unexpected end of payload");
+
+        // offset above u128::MAX
+        payload[0] = 1;
+        assert_eq!(decode_err(&payload), "uint out of range");
+    }
+
+    #[test]
+    fn rejects_out_of_range_lengths() {
+        let mut payload = payload(IBC_TRANSFER_PAYLOAD);
+        // the receiver string starts at 0x240
+        set_word(&mut payload, 0x240, 1000);
+        assert_eq!(decode_err(&payload), "unexpected end of payload");
+
+        let mut payload = self::payload(IBC_TRANSFER_PAYLOAD);
+        // the pool ids array starts at 0x100
+        set_word(&mut payload, 0x100, u128::MAX);
+        assert_eq!(decode_err(&payload), "length out of range");
+
+        let mut payload = self::payload(IBC_TRANSFER_PAYLOAD);
+        set_word(&mut payload, 0x100, 1 << 40);
+        assert_eq!(decode_err(&payload), "array length out of bounds");
+
+        let mut payload = self::payload(IBC_TRANSFER_PAYLOAD);
+        // the token out denoms array starts at 0x160
+        set_word(&mut payload, 0x160, 20);
+        assert_eq!(decode_err(&payload), "array length out of bounds");
+
+        let mut payload = self::payload(IBC_TRANSFER_PAYLOAD);
+        // the second pool id does not fit in a uint64
+        set_word(&mut payload, 0x140, u128::from(u64::MAX) + 1);
+        assert_eq!(decode_err(&payload), "uint64 out of range");
+    }
+
+    #[test]
+    fn rejects_pool_id_and_denom_length_mismatch() {
+        let mut payload = payload(IBC_TRANSFER_PAYLOAD);
+        // one pool id for two token out denoms
+        set_word(&mut payload, 0x100, 1);
+
+        assert_eq!(
+            decode_err(&payload),
+            "pool ids and token out denoms differ in length"
+        );
+    }
+}
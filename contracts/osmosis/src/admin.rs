@@ -0,0 +1,430 @@
+use cosmwasm_std::{BankMsg, Deps, DepsMut, MessageInfo, Response};
+use osmosis_router::OsmosisPath;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+
+use crate::{
+    commands::{integrator_payout_msg, BPS_DENOMINATOR},
+    state::{
+        self, clear_accrued_fees, load_accrued_fees, load_config, load_integrator_optional,
+        load_ownership, load_pause_state, remove_channel_packet_lifetime,
+        store_channel_packet_lifetime, store_config, store_integrator, store_ownership,
+        store_pause_state, store_route, AxelarConfig, Config, Integrator, PauseState,
+    },
+    ContractError,
+};
+
+pub fn validate_config(config: &Config) -> Result<(), ContractError> {
+    if config.ibc_packet_lifetime == 0 {
+        return Err(ContractError::InvalidConfig {
+            msg: "ibc packet lifetime must be non-zero".to_owned(),
+        });
+    }
+
+    if config.protocol_fee_bps >= BPS_DENOMINATOR {
+        return Err(ContractError::InvalidConfig {
+            msg: format!("protocol fee must be below {BPS_DENOMINATOR} bps"),
+        });
+    }
+
+    if let Some(axelar) = &config.axelar {
+        if axelar.channel.is_empty() || axelar.gateway_address.is_empty() {
+            return Err(ContractError::InvalidConfig {
+                msg: "axelar channel and gateway address must be set".to_owned(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    info: &MessageInfo,
+    ibc_packet_lifetime: Option<u64>,
+    protocol_fee_bps: Option<u16>,
+    axelar: Option<AxelarConfig>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    let mut config = load_config(deps.storage)?;
+    if let Some(ibc_packet_lifetime) = ibc_packet_lifetime {
+        config.ibc_packet_lifetime = ibc_packet_lifetime;
+    }
+    if let Some(protocol_fee_bps) = protocol_fee_bps {
+        config.protocol_fee_bps = protocol_fee_bps;
+    }
+    if axelar.is_some() {
+        config.axelar = axelar;
+    }
+
+    validate_config(&config)?;
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::new())
+}
+
+pub fn transfer_ownership(
+    deps: DepsMut,
+    info: &MessageInfo,
+    new_owner: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    let mut ownership = load_ownership(deps.storage)?;
+    ownership.pending_owner = Some(deps.api.addr_validate(&new_owner)?);
+    store_ownership(deps.storage, &ownership)?;
+
+    Ok(Response::new())
+}
+
+pub fn accept_ownership(deps: DepsMut, info: &MessageInfo) -> Result<Response, ContractError> {
+    let mut ownership = load_ownership(deps.storage)?;
+    if ownership.pending_owner.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    ownership.owner = info.sender.clone();
+    ownership.pending_owner = None;
+    store_ownership(deps.storage, &ownership)?;
+
+    Ok(Response::new())
+}
+
+pub fn set_guardian(
+    deps: DepsMut,
+    info: &MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    let mut config = load_config(deps.storage)?;
+    config.guardian = guardian
+        .map(|guardian| deps.api.addr_validate(&guardian))
+        .transpose()?;
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::new())
+}
+
+pub fn set_pause_state(
+    deps: DepsMut,
+    info: &MessageInfo,
+    swaps: Option<bool>,
+    multi_swap: Option<bool>,
+    ibc_transfers: Option<bool>,
+) -> Result<Response, ContractError> {
+    let is_owner = load_ownership(deps.storage)?.owner == info.sender;
+    let is_guardian = load_config(deps.storage)?.guardian.as_ref() == Some(&info.sender);
+    if !is_owner && !is_guardian {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // guardian is only allowed to pause
+    let unpauses = [swaps, multi_swap, ibc_transfers].contains(&Some(false));
+    if !is_owner && unpauses {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pause_state = load_pause_state(deps.storage)?;
+    let pause_state = PauseState {
+        swaps: swaps.unwrap_or(pause_state.swaps),
+        multi_swap: multi_swap.unwrap_or(pause_state.multi_swap),
+        ibc_transfers: ibc_transfers.unwrap_or(pause_state.ibc_transfers),
+    };
+    store_pause_state(deps.storage, &pause_state)?;
+
+    Ok(Response::new())
+}
+
+pub fn withdraw_fees(
+    deps: DepsMut,
+    info: &MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
+
+    let fees = load_accrued_fees(deps.storage)?;
+    if fees.is_empty() {
+        return Err(ContractError::NoAccruedFees {});
+    }
+    clear_accrued_fees(deps.storage);
+
+    Ok(Response::new().add_message(BankMsg::Send {
+        to_address: recipient.to_string(),
+        amount: fees,
+    }))
+}
+
+pub fn set_channel_packet_lifetime(
+    deps: DepsMut,
+    info: &MessageInfo,
+    channel: String,
+    packet_lifetime: Option<u64>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    match packet_lifetime {
+        Some(0) => {
+            return Err(ContractError::InvalidConfig {
+                msg: "ibc packet lifetime must be non-zero".to_owned(),
+            })
+        }
+        Some(packet_lifetime) => {
+            store_channel_packet_lifetime(deps.storage, &channel, packet_lifetime)?
+        }
+        None => remove_channel_packet_lifetime(deps.storage, &channel),
+    }
+
+    Ok(Response::new())
+}
+
+pub fn set_integrator(
+    deps: DepsMut,
+    info: &MessageInfo,
+    id: String,
+    fee_bps: u16,
+    payout_address: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    if fee_bps >= BPS_DENOMINATOR {
+        return Err(ContractError::InvalidConfig {
+            msg: format!("integrator fee must be below {BPS_DENOMINATOR} bps"),
+        });
+    }
+
+    store_integrator(
+        deps.storage,
+        &id,
+        &Integrator {
+            fee_bps,
+            payout_address: deps.api.addr_validate(&payout_address)?,
+        },
+    )?;
+
+    Ok(Response::new())
+}
+
+pub fn remove_integrator(
+    deps: DepsMut,
+    info: &MessageInfo,
+    id: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    let Some(integrator) = load_integrator_optional(deps.storage, &id)? else {
+        return Err(ContractError::IntegratorNotFound { id });
+    };
+    let payout = integrator_payout_msg(deps.storage, &id, &integrator)?;
+    state::remove_integrator(deps.storage, &id);
+
+    Ok(Response::new().add_messages(payout))
+}
+
+pub fn set_route(
+    deps: DepsMut,
+    info: &MessageInfo,
+    input_denom: String,
+    output_denom: String,
+    path: Vec<SwapAmountInRoute>,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    OsmosisPath::new(path.clone()).validate_path(&input_denom, Some(&output_denom))?;
+    store_route(deps.storage, &input_denom, &output_denom, &path)?;
+
+    Ok(Response::new())
+}
+
+pub fn remove_route(
+    deps: DepsMut,
+    info: &MessageInfo,
+    input_denom: String,
+    output_denom: String,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    state::remove_route(deps.storage, &input_denom, &output_denom);
+
+    Ok(Response::new())
+}
+
+pub fn ensure_owner(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    if load_ownership(deps.storage)?.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_info},
+        Addr,
+    };
+
+    use super::*;
+    use crate::state::Ownership;
+
+    fn setup(deps: DepsMut) {
+        store_ownership(
+            deps.storage,
+            &Ownership {
+                owner: Addr::unchecked("owner"),
+                pending_owner: None,
+            },
+        )
+        .unwrap();
+        store_config(
+            deps.storage,
+            &Config {
+                ibc_packet_lifetime: 60,
+                guardian: Some(Addr::unchecked("guardian")),
+                protocol_fee_bps: 0,
+                axelar: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn only_owner_updates_config() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        for sender in ["guardian", "anyone"] {
+            let err = update_config(
+                deps.as_mut(),
+                &mock_info(sender, &[]),
+                Some(120),
+                None,
+                None,
+            )
+            .unwrap_err();
+            assert!(matches!(err, ContractError::Unauthorized {}));
+        }
+        assert_eq!(load_config(&deps.storage).unwrap().ibc_packet_lifetime, 60);
+
+        update_config(
+            deps.as_mut(),
+            &mock_info("owner", &[]),
+            Some(120),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(load_config(&deps.storage).unwrap().ibc_packet_lifetime, 120);
+    }
+
+    #[test]
+    fn only_pending_owner_accepts_ownership() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let err = transfer_ownership(
+            deps.as_mut(),
+            &mock_info("anyone", &[]),
+            "anyone".to_owned(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        transfer_ownership(
+            deps.as_mut(),
+            &mock_info("owner", &[]),
+            "new_owner".to_owned(),
+        )
+        .unwrap();
+
+        for sender in ["owner", "anyone"] {
+            let err = accept_ownership(deps.as_mut(), &mock_info(sender, &[])).unwrap_err();
+            assert!(matches!(err, ContractError::Unauthorized {}));
+        }
+
+        accept_ownership(deps.as_mut(), &mock_info("new_owner", &[])).unwrap();
+        assert_eq!(
+            load_ownership(&deps.storage).unwrap(),
+            Ownership {
+                owner: Addr::unchecked("new_owner"),
+                pending_owner: None,
+            }
+        );
+
+        // the accepted transfer can't be accepted again, and the previous owner lost its rights
+        let err = accept_ownership(deps.as_mut(), &mock_info("new_owner", &[])).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = ensure_owner(deps.as_ref(), &mock_info("owner", &[])).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn guardian_pauses_but_does_not_unpause() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let pause = |swaps, multi_swap, ibc_transfers| PauseState {
+            swaps,
+            multi_swap,
+            ibc_transfers,
+        };
+
+        let err = set_pause_state(
+            deps.as_mut(),
+            &mock_info("anyone", &[]),
+            Some(true),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        set_pause_state(
+            deps.as_mut(),
+            &mock_info("guardian", &[]),
+            Some(true),
+            Some(true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            load_pause_state(&deps.storage).unwrap(),
+            pause(true, true, false)
+        );
+
+        // also when pausing another operation at the same time
+        for (swaps, ibc_transfers) in [(Some(false), None), (Some(false), Some(true))] {
+            let err = set_pause_state(
+                deps.as_mut(),
+                &mock_info("guardian", &[]),
+                swaps,
+                None,
+                ibc_transfers,
+            )
+            .unwrap_err();
+            assert!(matches!(err, ContractError::Unauthorized {}));
+        }
+        assert_eq!(
+            load_pause_state(&deps.storage).unwrap(),
+            pause(true, true, false)
+        );
+
+        set_pause_state(
+            deps.as_mut(),
+            &mock_info("owner", &[]),
+            Some(false),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            load_pause_state(&deps.storage).unwrap(),
+            pause(false, true, false)
+        );
+    }
+}
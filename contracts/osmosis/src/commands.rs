@@ -1,38 +1,111 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 
-use cw_multi_test::Contract;
 use ::prost::Message;
 
 use cosmwasm_std::{
-    to_binary, BankMsg, Deps, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, SubMsgResponse,
-    SubMsgResult, WasmMsg, Coin, Decimal, Uint128,
+    to_binary, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Event, MessageInfo,
+    Reply, Response, StdError, StdResult, Storage, SubMsg, SubMsgResponse, SubMsgResult, Timestamp,
+    Uint128, WasmMsg,
 };
 use cw_utils::one_coin;
+use multicall::injection::inject_amount;
 use osmosis_router::{
-    router::{build_swap_msg, get_swap_amount_out_response},
-    OsmosisSwapMsg,
+    router::{build_swap_exact_amount_out_msg, build_swap_msg, get_swap_amount_out_response},
+    state::remove_processing_swap,
+    OsmosisSwapExactAmountOutMsg, OsmosisSwapMsg, TwapSlippage,
 };
 use osmosis_std::types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier;
-use error::OsmosisRouterError;
+use serde::Serialize;
 
 use crate::{
+    abi::decode_gmp_swap_request,
+    events,
     msg::{
-        AfterSwapAction, ExecuteMsg, MsgReplyId, MsgTransfer, MsgTransferResponse, MultiSwapMsg,
-        PriceImpactTradeResponse,
+        AfterSwapAction, ExecuteMsg, ForwardHop, GmpMessageType, IbcCounterpartyHeight, MsgReplyId,
+        MsgTransfer, MsgTransferResponse, MultiSwapMsg, PriceImpactTradeResponse, SerializableJson,
+        SplitAction, SplitShare,
     },
     state::{
-        load_ibc_transfer_reply_state, load_multi_swap_state, load_swap_reply_state,
-        remove_multi_swap_state, store_awaiting_ibc_transfer, store_ibc_transfer_reply_state,
+        add_accrued_fee, add_integrator_fee, clear_integrator_fees, load_channel_packet_lifetime,
+        load_config, load_custom_call_reply_state, load_ibc_transfer_reply_state,
+        load_integrator_fees, load_integrator_optional, load_multi_swap_state, load_pause_state,
+        load_route_optional, load_swap_reply_state, next_correlation_id, remove_multi_swap_state,
+        store_awaiting_ibc_transfer, store_custom_call_reply_state, store_ibc_transfer_reply_state,
         store_multi_swap_state, store_swap_reply_state, swap_reply_state_exists,
-        IbcTransferReplyState, MultiSwapState, SwapReplyState,
+        CustomCallReplyState, IbcTransferReplyState, Integrator, MultiSwapState, SwapReplyState,
     },
     ContractError,
 };
 
-const TRANSFER_PORT: &'static str = "transfer";
-const IBC_CALLBACK: &'static str = "ibc_callback";
-const IBC_PACKET_LIFITIME: u64 = 604_800u64;
+pub const BPS_DENOMINATOR: u16 = 10_000;
 
+pub const TRANSFER_PORT: &str = "transfer";
+const IBC_CALLBACK: &str = "ibc_callback";
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const AXELAR_GMP_MESSAGE: u8 = 1;
+const AXELAR_GMP_MESSAGE_WITH_TOKEN: u8 = 2;
+
+struct IbcTransferParams {
+    channel: String,
+    receiver: String,
+    memo: String,
+    timeout_timestamp: Option<Timestamp>,
+    timeout_height: Option<IbcCounterpartyHeight>,
+}
+
+/// Packet forward middleware memo, `timeout` is in nanoseconds.
+#[derive(Serialize)]
+struct ForwardMemo {
+    forward: Forward,
+}
+
+#[derive(Serialize)]
+struct Forward {
+    receiver: String,
+    port: &'static str,
+    channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<Box<serde_cw_value::Value>>,
+}
+
+/// Memo understood by the Axelar GMP module, `type` is 1 for messages and 2 for messages with
+/// token.
+#[derive(Serialize)]
+struct AxelarGmpMemo<'a> {
+    destination_chain: &'a str,
+    destination_address: &'a str,
+    payload: &'a [u8],
+    #[serde(rename = "type")]
+    message_type: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee: Option<AxelarGmpFee<'a>>,
+    ibc_callback: &'a str,
+}
+
+#[derive(Serialize)]
+struct AxelarGmpFee<'a> {
+    amount: String,
+    recipient: &'a str,
+}
+
+/// ibc-hooks memo executing `msg` on `contract` with the transferred funds.
+#[derive(Serialize)]
+struct RemoteSquidMemo<'a> {
+    wasm: WasmHook<'a>,
+    ibc_callback: &'a str,
+}
+
+#[derive(Serialize)]
+struct WasmHook<'a> {
+    contract: &'a str,
+    msg: &'a ExecuteMsg,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn swap(
     deps: DepsMut,
     env: &Env,
@@ -40,7 +113,151 @@ pub fn swap(
     swap_msg: OsmosisSwapMsg,
     after_swap_action: AfterSwapAction,
     local_fallback_address: String,
+    integrator_id: Option<String>,
+    refund_on_swap_failure: bool,
+) -> Result<Response, ContractError> {
+    let input_coin = one_coin(info)?;
+    let pool_path = events::pool_path(&swap_msg.path, &swap_msg.split_routes);
+    let swap_msg = build_swap_msg(
+        deps.storage,
+        &deps.querier,
+        env,
+        input_coin.clone(),
+        swap_msg,
+    )?;
+
+    let correlation_id = next_correlation_id(deps.storage)?;
+
+    dispatch_swap(
+        deps,
+        swap_msg,
+        SwapReplyState {
+            correlation_id,
+            input_coin,
+            pool_path,
+            after_swap_action,
+            local_fallback_address,
+            integrator_id,
+        },
+        refund_on_swap_failure,
+    )
+}
+
+pub fn swap_exact_amount_out(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    mut swap_msg: OsmosisSwapExactAmountOutMsg,
+    after_swap_action: AfterSwapAction,
+    local_fallback_address: String,
+    refund_on_swap_failure: bool,
+) -> Result<Response, ContractError> {
+    let input_coin = one_coin(info)?;
+    let pool_path = events::pool_path(&swap_msg.path, &[]);
+
+    // the protocol fee is swapped for on top, so the requested amount remains after it
+    let protocol_fee_bps = load_config(deps.storage)?.protocol_fee_bps;
+    let token_out_amount = Uint128::from_str(&swap_msg.token_out_amount)?;
+    swap_msg.token_out_amount = add_fee(token_out_amount, protocol_fee_bps)?.to_string();
+
+    let swap_msg =
+        build_swap_exact_amount_out_msg(deps.storage, env, input_coin.clone(), swap_msg)?;
+
+    let correlation_id = next_correlation_id(deps.storage)?;
+
+    dispatch_swap(
+        deps,
+        swap_msg,
+        SwapReplyState {
+            correlation_id,
+            input_coin,
+            pool_path,
+            after_swap_action,
+            local_fallback_address,
+            integrator_id: None,
+        },
+        refund_on_swap_failure,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap_with_registered_route(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    output_denom: String,
+    token_out_min_amount: Option<String>,
+    twap_slippage: Option<TwapSlippage>,
+    after_swap_action: AfterSwapAction,
+    local_fallback_address: String,
+    refund_on_swap_failure: bool,
 ) -> Result<Response, ContractError> {
+    let input_coin = one_coin(info)?;
+    let Some(path) = load_route_optional(deps.storage, &input_coin.denom, &output_denom)? else {
+        return Err(ContractError::RouteNotFound {
+            input_denom: input_coin.denom,
+            output_denom,
+        });
+    };
+
+    let swap_msg = OsmosisSwapMsg {
+        token_out_min_amount,
+        path,
+        split_routes: vec![],
+        twap_slippage,
+    };
+
+    swap(
+        deps,
+        env,
+        info,
+        swap_msg,
+        after_swap_action,
+        local_fallback_address,
+        None,
+        refund_on_swap_failure,
+    )
+}
+
+pub fn axelar_gmp_swap(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    source_chain: String,
+    source_address: String,
+    payload: Binary,
+) -> Result<Response, ContractError> {
+    let request = decode_gmp_swap_request(&payload)?;
+
+    let response = swap(
+        deps,
+        env,
+        info,
+        request.swap_msg,
+        request.after_swap_action,
+        request.local_fallback_address,
+        None,
+        false,
+    )?;
+
+    // anyone can call this entry point, the source is reported as claimed by the caller
+    Ok(response
+        .add_attribute("claimed_source_chain", source_chain)
+        .add_attribute("claimed_source_address", source_address))
+}
+
+fn dispatch_swap(
+    deps: DepsMut,
+    swap_msg: CosmosMsg,
+    swap_reply_state: SwapReplyState,
+    refund_on_swap_failure: bool,
+) -> Result<Response, ContractError> {
+    if load_pause_state(deps.storage)?.swaps {
+        return Err(ContractError::Paused {
+            operation: "swap".to_owned(),
+        });
+    }
+
     // re-entrancy check
     if swap_reply_state_exists(deps.storage)? {
         return Err(ContractError::ContractLocked {
@@ -48,18 +265,20 @@ pub fn swap(
         });
     }
 
-    let input_coin = one_coin(info)?;
-    let swap_msg = build_swap_msg(deps.storage, env, input_coin, swap_msg)?;
+    if let Some(id) = &swap_reply_state.integrator_id {
+        ensure_integrator_exists(deps.as_ref(), id)?;
+    }
+    validate_after_swap_action(&swap_reply_state.after_swap_action)?;
 
-    store_swap_reply_state(
-        deps.storage,
-        &SwapReplyState {
-            after_swap_action,
-            local_fallback_address,
-        },
-    )?;
+    store_swap_reply_state(deps.storage, &swap_reply_state)?;
+
+    // failures are handled in `handle_after_swap_action`
+    let swap_msg = match refund_on_swap_failure {
+        true => SubMsg::reply_always(swap_msg, MsgReplyId::Swap.repr()),
+        false => SubMsg::reply_on_success(swap_msg, MsgReplyId::Swap.repr()),
+    };
 
-    Ok(Response::new().add_submessage(SubMsg::reply_on_success(swap_msg, MsgReplyId::Swap.repr())))
+    Ok(Response::new().add_submessage(swap_msg))
 }
 
 pub fn handle_after_swap_action(
@@ -67,36 +286,148 @@ pub fn handle_after_swap_action(
     env: &Env,
     reply: Reply,
 ) -> Result<Response, ContractError> {
-    let output_token_info = get_swap_amount_out_response(deps.storage, reply)?;
+    if let SubMsgResult::Err(error) = reply.result {
+        return handle_failed_swap(deps, error);
+    }
+
+    let mut output_token_info = get_swap_amount_out_response(deps.storage, reply)?;
     let after_swap_info = load_swap_reply_state(deps.storage)?;
 
-    let response = match after_swap_info.after_swap_action {
+    take_fees(
+        deps.storage,
+        after_swap_info.integrator_id.as_deref(),
+        &mut output_token_info.output_coin,
+    )?;
+
+    let correlation_id = after_swap_info.correlation_id;
+    let output_coin = output_token_info.output_coin;
+    let refund_coin = output_token_info.refund_coin;
+    let mut input_coin = after_swap_info.input_coin;
+    if let Some(refund_coin) = &refund_coin {
+        input_coin.amount = input_coin.amount.saturating_sub(refund_coin.amount);
+    }
+
+    let swap_event = events::swap(
+        correlation_id,
+        &input_coin,
+        &output_coin,
+        &after_swap_info.pool_path,
+    );
+    let after_action_event = events::after_action(
+        correlation_id,
+        &after_swap_info.after_swap_action,
+        &output_coin,
+    );
+    let after_swap_msgs = build_after_swap_action_msgs(
+        deps,
+        env,
+        after_swap_info.after_swap_action,
+        output_coin,
+        &after_swap_info.local_fallback_address,
+        correlation_id,
+    )?;
+
+    let mut response = Response::new()
+        .add_event(swap_event)
+        .add_event(after_action_event)
+        .add_submessages(after_swap_msgs);
+
+    // unused input of an exact amount out swap goes back to the fallback address
+    if let Some(refund_coin) = refund_coin {
+        response = response
+            .add_event(events::refund(
+                Some(correlation_id),
+                &after_swap_info.local_fallback_address,
+                &refund_coin,
+                "unused_input",
+            ))
+            .add_message(BankMsg::Send {
+                to_address: after_swap_info.local_fallback_address,
+                amount: vec![refund_coin],
+            });
+    }
+
+    Ok(response)
+}
+
+/// Refunds the input of a swap dispatched with `refund_on_swap_failure`.
+fn handle_failed_swap(deps: DepsMut, error: String) -> Result<Response, ContractError> {
+    let after_swap_info = load_swap_reply_state(deps.storage)?;
+    remove_processing_swap(deps.storage);
+
+    Ok(Response::new()
+        .add_event(events::refund(
+            Some(after_swap_info.correlation_id),
+            &after_swap_info.local_fallback_address,
+            &after_swap_info.input_coin,
+            "swap_failed",
+        ))
+        .add_message(BankMsg::Send {
+            to_address: after_swap_info.local_fallback_address,
+            amount: vec![after_swap_info.input_coin],
+        })
+        .add_event(Event::new("swap_failed").add_attribute("error", error)))
+}
+
+fn build_after_swap_action_msgs(
+    mut deps: DepsMut,
+    env: &Env,
+    after_swap_action: AfterSwapAction,
+    output_coin: Coin,
+    local_fallback_address: &str,
+    correlation_id: u64,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let msgs = match after_swap_action {
         AfterSwapAction::BankSend { receiver } => {
             let bank = BankMsg::Send {
                 to_address: receiver,
-                amount: vec![output_token_info.output_coin],
+                amount: vec![output_coin],
             };
-            Response::new().add_message(bank)
+            vec![SubMsg::new(bank)]
         }
         AfterSwapAction::CustomCall {
             contract_address,
-            msg,
+            mut msg,
+            balance_injections,
         } => {
+            // the contract balance also holds accrued fees and the other split legs
+            inject_amount(&mut msg, &balance_injections, &output_coin)?;
+
             let wasm = WasmMsg::Execute {
                 contract_addr: contract_address,
                 msg: to_binary(&msg)?,
-                funds: vec![output_token_info.output_coin],
+                funds: vec![output_coin.clone()],
             };
-            Response::new().add_message(wasm)
+
+            // a failing call refunds the output instead of reverting the swap
+            store_custom_call_reply_state(
+                deps.storage,
+                &CustomCallReplyState {
+                    correlation_id,
+                    local_fallback_address: local_fallback_address.to_owned(),
+                    coin: output_coin,
+                },
+            )?;
+
+            vec![SubMsg::reply_always(wasm, MsgReplyId::CustomCall.repr())]
         }
         AfterSwapAction::IbcTransfer {
             receiver,
             channel,
             next_memo,
+            timeout_timestamp,
+            timeout_height,
+            forward_hops,
         } => {
+            let next_memo = match forward_hops.is_empty() {
+                true => next_memo,
+                false => Some(build_forward_memo(forward_hops, next_memo)?),
+            };
             let next_memo = next_memo.unwrap_or_else(|| serde_json_wasm::from_str("{}").unwrap());
             let next_memo = {
-                let serde_cw_value::Value::Map(mut m) = next_memo.0 else { unreachable!() };
+                let serde_cw_value::Value::Map(mut m) = next_memo.0 else {
+                    return Err(ContractError::InvalidMemo {});
+                };
                 m.insert(
                     serde_cw_value::Value::String(IBC_CALLBACK.to_owned()),
                     serde_cw_value::Value::String(env.contract.address.to_string()),
@@ -107,35 +438,508 @@ pub fn handle_after_swap_action(
             let memo = serde_json_wasm::to_string(&next_memo)
                 .map_err(|_e| ContractError::InvalidMemo {})?;
 
-            let ibc_transfer = MsgTransfer {
-                source_port: TRANSFER_PORT.to_owned(),
-                source_channel: channel.clone(),
-                token: Some(output_token_info.output_coin.clone().into()),
-                sender: env.contract.address.to_string(),
-                receiver,
-                timeout_height: None,
-                timeout_timestamp: Some(env.block.time.plus_seconds(IBC_PACKET_LIFITIME).nanos()),
-                memo,
-            };
+            vec![build_ibc_transfer_msg(
+                deps.storage,
+                env,
+                IbcTransferParams {
+                    channel,
+                    receiver,
+                    memo,
+                    timeout_timestamp,
+                    timeout_height,
+                },
+                output_coin,
+                local_fallback_address,
+                correlation_id,
+            )?]
+        }
+        AfterSwapAction::RemoteSquidCall {
+            channel,
+            contract_address,
+            msg,
+            timeout_timestamp,
+            timeout_height,
+        } => {
+            let memo = serde_json_wasm::to_string(&RemoteSquidMemo {
+                wasm: WasmHook {
+                    contract: &contract_address,
+                    msg: &msg,
+                },
+                ibc_callback: env.contract.address.as_str(),
+            })
+            .map_err(|_e| ContractError::InvalidMemo {})?;
 
-            store_ibc_transfer_reply_state(
+            // ibc-hooks requires the receiver to be the called contract
+            vec![build_ibc_transfer_msg(
                 deps.storage,
-                &IbcTransferReplyState {
-                    local_fallback_address: after_swap_info.local_fallback_address,
+                env,
+                IbcTransferParams {
                     channel,
-                    denom: output_token_info.output_coin.denom,
-                    amount: output_token_info.output_coin.amount,
+                    receiver: contract_address,
+                    memo,
+                    timeout_timestamp,
+                    timeout_height,
                 },
-            )?;
+                output_coin,
+                local_fallback_address,
+                correlation_id,
+            )?]
+        }
+        AfterSwapAction::AxelarGmp {
+            destination_chain,
+            destination_address,
+            payload,
+            message_type,
+            fee,
+        } => {
+            let Some(axelar) = load_config(deps.storage)?.axelar else {
+                return Err(ContractError::AxelarNotConfigured {});
+            };
 
-            Response::new().add_submessage(SubMsg::reply_on_success(
-                ibc_transfer,
-                MsgReplyId::IbcTransfer.repr(),
-            ))
+            if let Some(fee) = &fee {
+                if fee.amount >= output_coin.amount {
+                    return Err(ContractError::InvalidAxelarGmp {
+                        msg: format!("fee {} exceeds swap output {}", fee.amount, output_coin),
+                    });
+                }
+            }
+
+            let memo = serde_json_wasm::to_string(&AxelarGmpMemo {
+                destination_chain: &destination_chain,
+                destination_address: &destination_address,
+                payload: payload.as_slice(),
+                message_type: match message_type {
+                    GmpMessageType::Message => AXELAR_GMP_MESSAGE,
+                    GmpMessageType::MessageWithToken => AXELAR_GMP_MESSAGE_WITH_TOKEN,
+                },
+                fee: fee.as_ref().map(|fee| AxelarGmpFee {
+                    amount: fee.amount.to_string(),
+                    recipient: &fee.recipient,
+                }),
+                ibc_callback: env.contract.address.as_str(),
+            })
+            .map_err(|_e| ContractError::InvalidMemo {})?;
+
+            vec![build_ibc_transfer_msg(
+                deps.storage,
+                env,
+                IbcTransferParams {
+                    channel: axelar.channel,
+                    receiver: axelar.gateway_address,
+                    memo,
+                    timeout_timestamp: None,
+                    timeout_height: None,
+                },
+                output_coin,
+                local_fallback_address,
+                correlation_id,
+            )?]
+        }
+        AfterSwapAction::Split { actions } => {
+            let (amounts, remainder) = split_output(output_coin.amount, &actions)?;
+
+            // built last to first so the reply states of the first action end up in front
+            let mut msgs = vec![];
+            for (split_action, amount) in actions.into_iter().zip(amounts).rev() {
+                if amount.is_zero() {
+                    continue;
+                }
+
+                let action_msgs = build_after_swap_action_msgs(
+                    deps.branch(),
+                    env,
+                    split_action.action,
+                    Coin {
+                        denom: output_coin.denom.clone(),
+                        amount,
+                    },
+                    local_fallback_address,
+                    correlation_id,
+                )?;
+                msgs.splice(0..0, action_msgs);
+            }
+
+            if !remainder.is_zero() {
+                msgs.push(SubMsg::new(BankMsg::Send {
+                    to_address: local_fallback_address.to_owned(),
+                    amount: vec![Coin {
+                        denom: output_coin.denom,
+                        amount: remainder,
+                    }],
+                }));
+            }
+
+            msgs
         }
     };
 
-    Ok(response)
+    Ok(msgs)
+}
+
+/// Nests the hops into packet forward middleware memos, the first hop being the outermost.
+fn build_forward_memo(
+    forward_hops: Vec<ForwardHop>,
+    next_memo: Option<SerializableJson>,
+) -> Result<SerializableJson, ContractError> {
+    let mut next = next_memo.map(|memo| memo.0);
+    for hop in forward_hops.into_iter().rev() {
+        let forward = ForwardMemo {
+            forward: Forward {
+                receiver: hop.receiver,
+                port: TRANSFER_PORT,
+                channel: hop.channel,
+                timeout: hop
+                    .timeout_seconds
+                    .map(|seconds| seconds.saturating_mul(NANOS_PER_SECOND)),
+                retries: hop.retries,
+                next: next.map(Box::new),
+            },
+        };
+        next = Some(serde_cw_value::to_value(forward).map_err(|_e| ContractError::InvalidMemo {})?);
+    }
+
+    Ok(SerializableJson(next.ok_or(ContractError::InvalidMemo {})?))
+}
+
+fn build_ibc_transfer_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    params: IbcTransferParams,
+    coin: Coin,
+    local_fallback_address: &str,
+    correlation_id: u64,
+) -> Result<SubMsg, ContractError> {
+    // fails the whole swap, so the user keeps the input funds
+    if load_pause_state(storage)?.ibc_transfers {
+        return Err(ContractError::Paused {
+            operation: "ibc transfer".to_owned(),
+        });
+    }
+
+    let (timeout_height, timeout_timestamp) = get_ibc_timeout(
+        storage,
+        env,
+        &params.channel,
+        params.timeout_timestamp,
+        params.timeout_height,
+    )?;
+    let ibc_transfer = MsgTransfer {
+        source_port: TRANSFER_PORT.to_owned(),
+        source_channel: params.channel.clone(),
+        token: Some(coin.clone().into()),
+        sender: env.contract.address.to_string(),
+        receiver: params.receiver.clone(),
+        timeout_height,
+        timeout_timestamp,
+        memo: params.memo,
+    };
+
+    // every ibc leg gets its own reply, they are handled in dispatch order
+    store_ibc_transfer_reply_state(
+        storage,
+        &IbcTransferReplyState {
+            local_fallback_address: local_fallback_address.to_owned(),
+            channel: params.channel,
+            denom: coin.denom,
+            amount: coin.amount,
+            timeout_timestamp,
+            correlation_id: Some(correlation_id),
+            receiver: params.receiver,
+        },
+    )?;
+
+    Ok(SubMsg::reply_on_success(
+        ibc_transfer,
+        MsgReplyId::IbcTransfer.repr(),
+    ))
+}
+
+/// Uses the requested timeouts, or the packet lifetime of the channel if none is given.
+fn get_ibc_timeout(
+    storage: &dyn Storage,
+    env: &Env,
+    channel: &str,
+    timeout_timestamp: Option<Timestamp>,
+    timeout_height: Option<IbcCounterpartyHeight>,
+) -> Result<(Option<IbcCounterpartyHeight>, Option<u64>), ContractError> {
+    if let Some(timeout_timestamp) = timeout_timestamp {
+        if timeout_timestamp <= env.block.time {
+            return Err(ContractError::InvalidIbcTimeout {
+                msg: "timeout timestamp must be in the future".to_owned(),
+            });
+        }
+    }
+
+    if let Some(height) = &timeout_height {
+        if height.revision_height.unwrap_or_default() == 0 {
+            return Err(ContractError::InvalidIbcTimeout {
+                msg: "timeout height must be non-zero".to_owned(),
+            });
+        }
+    }
+
+    if timeout_timestamp.is_none() && timeout_height.is_none() {
+        let packet_lifetime = load_channel_packet_lifetime(storage, channel)?;
+        let timeout_timestamp = env.block.time.plus_seconds(packet_lifetime).nanos();
+        return Ok((None, Some(timeout_timestamp)));
+    }
+
+    let timeout_timestamp = timeout_timestamp.map(|timestamp| timestamp.nanos());
+    Ok((timeout_height, timeout_timestamp))
+}
+
+pub fn validate_after_swap_action(
+    after_swap_action: &AfterSwapAction,
+) -> Result<(), ContractError> {
+    match after_swap_action {
+        AfterSwapAction::Split { actions } => validate_split(actions),
+        AfterSwapAction::IbcTransfer {
+            next_memo,
+            forward_hops,
+            ..
+        } => {
+            // the ibc callback is added to the memo, which therefore has to be an object
+            if let Some(next_memo) = next_memo {
+                if !matches!(next_memo.0, serde_cw_value::Value::Map(_)) {
+                    return Err(ContractError::InvalidMemo {});
+                }
+            }
+
+            validate_forward_hops(forward_hops)
+        }
+        AfterSwapAction::AxelarGmp {
+            destination_chain,
+            destination_address,
+            ..
+        } => {
+            if destination_chain.is_empty() || destination_address.is_empty() {
+                return Err(ContractError::InvalidAxelarGmp {
+                    msg: "destination chain and address must be set".to_owned(),
+                });
+            }
+
+            Ok(())
+        }
+        AfterSwapAction::RemoteSquidCall {
+            contract_address,
+            msg,
+            ..
+        } => validate_remote_squid_call(contract_address, msg),
+        _ => Ok(()),
+    }
+}
+
+fn validate_split(actions: &[SplitAction]) -> Result<(), ContractError> {
+    if actions.is_empty() {
+        return Err(ContractError::InvalidSplit {
+            msg: "split must contain at least one action".to_owned(),
+        });
+    }
+
+    for split_action in actions {
+        if matches!(split_action.action, AfterSwapAction::Split { .. }) {
+            return Err(ContractError::InvalidSplit {
+                msg: "split actions can not be nested".to_owned(),
+            });
+        }
+
+        let is_zero = match split_action.share {
+            SplitShare::Weight(weight) => weight == 0,
+            SplitShare::Amount(amount) => amount.is_zero(),
+        };
+        if is_zero {
+            return Err(ContractError::InvalidSplit {
+                msg: "split shares must be non-zero".to_owned(),
+            });
+        }
+
+        validate_after_swap_action(&split_action.action)?;
+    }
+
+    Ok(())
+}
+
+fn validate_forward_hops(forward_hops: &[ForwardHop]) -> Result<(), ContractError> {
+    let is_invalid = forward_hops
+        .iter()
+        .any(|hop| hop.channel.is_empty() || hop.receiver.is_empty());
+    if is_invalid {
+        return Err(ContractError::InvalidForwardHop {
+            msg: "channel and receiver must be set".to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+/// The remote contract lives on another chain, so only the bech32 encoding can be checked.
+fn validate_remote_squid_call(
+    contract_address: &str,
+    msg: &ExecuteMsg,
+) -> Result<(), ContractError> {
+    bech32::decode(contract_address).map_err(|e| ContractError::InvalidRemoteSquidCall {
+        msg: format!("invalid contract address {contract_address}: {e}"),
+    })?;
+
+    match msg {
+        ExecuteMsg::SwapWithAction {
+            after_swap_action, ..
+        }
+        | ExecuteMsg::SwapExactAmountOutWithAction {
+            after_swap_action, ..
+        }
+        | ExecuteMsg::SwapWithRegisteredRoute {
+            after_swap_action, ..
+        } => validate_after_swap_action(after_swap_action),
+        _ => Err(ContractError::InvalidRemoteSquidCall {
+            msg: "remote message must be a single swap with action".to_owned(),
+        }),
+    }
+}
+
+/// Fixed amounts are paid first, the rest is divided by weight with the rounding dust going to
+/// the last weighted action. Without weighted actions the rest is returned as remainder.
+fn split_output(
+    total: Uint128,
+    actions: &[SplitAction],
+) -> Result<(Vec<Uint128>, Uint128), ContractError> {
+    let mut fixed_total = Uint128::zero();
+    let mut total_weight = 0u128;
+    for split_action in actions {
+        match split_action.share {
+            SplitShare::Weight(weight) => total_weight += u128::from(weight),
+            SplitShare::Amount(amount) => {
+                fixed_total = fixed_total.checked_add(amount).map_err(StdError::from)?
+            }
+        }
+    }
+
+    let Ok(weighted_total) = total.checked_sub(fixed_total) else {
+        return Err(ContractError::InvalidSplit {
+            msg: format!("fixed amounts {fixed_total} exceed swap output {total}"),
+        });
+    };
+    if total_weight == 0 {
+        let amounts = actions
+            .iter()
+            .map(|split_action| match split_action.share {
+                SplitShare::Weight(_) => Uint128::zero(),
+                SplitShare::Amount(amount) => amount,
+            })
+            .collect();
+        return Ok((amounts, weighted_total));
+    }
+
+    let last_weighted = actions
+        .iter()
+        .rposition(|split_action| matches!(split_action.share, SplitShare::Weight(_)));
+
+    let mut distributed = Uint128::zero();
+    let amounts = actions
+        .iter()
+        .enumerate()
+        .map(|(i, split_action)| match split_action.share {
+            SplitShare::Amount(amount) => amount,
+            SplitShare::Weight(_) if Some(i) == last_weighted => weighted_total - distributed,
+            SplitShare::Weight(weight) => {
+                let amount = weighted_total.multiply_ratio(u128::from(weight), total_weight);
+                distributed += amount;
+                amount
+            }
+        })
+        .collect();
+
+    Ok((amounts, Uint128::zero()))
+}
+
+/// Accrues the protocol fee and then the integrator fee and deducts both from the swap output.
+fn take_fees(
+    storage: &mut dyn Storage,
+    integrator_id: Option<&str>,
+    output_coin: &mut Coin,
+) -> Result<(), ContractError> {
+    let protocol_fee = deduct_fee(output_coin, load_config(storage)?.protocol_fee_bps);
+    if !protocol_fee.amount.is_zero() {
+        add_accrued_fee(storage, &protocol_fee)?;
+    }
+
+    let Some(id) = integrator_id else {
+        return Ok(());
+    };
+    let Some(integrator) = load_integrator_optional(storage, id)? else {
+        return Err(ContractError::IntegratorNotFound { id: id.to_owned() });
+    };
+
+    let integrator_fee = deduct_fee(output_coin, integrator.fee_bps);
+    if !integrator_fee.amount.is_zero() {
+        add_integrator_fee(storage, id, &integrator_fee)?;
+    }
+
+    Ok(())
+}
+
+/// Grosses `amount` up so that at least `amount` remains once `deduct_fee` took its fee.
+fn add_fee(amount: Uint128, fee_bps: u16) -> StdResult<Uint128> {
+    let fee = amount
+        .checked_multiply_ratio(fee_bps, BPS_DENOMINATOR - fee_bps)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    Ok(amount.checked_add(fee)?)
+}
+
+fn deduct_fee(coin: &mut Coin, fee_bps: u16) -> Coin {
+    let fee_amount = coin.amount.multiply_ratio(fee_bps, BPS_DENOMINATOR);
+    coin.amount -= fee_amount;
+
+    Coin {
+        denom: coin.denom.clone(),
+        amount: fee_amount,
+    }
+}
+
+fn ensure_integrator_exists(deps: Deps, id: &str) -> Result<(), ContractError> {
+    if load_integrator_optional(deps.storage, id)?.is_none() {
+        return Err(ContractError::IntegratorNotFound { id: id.to_owned() });
+    }
+
+    Ok(())
+}
+
+pub fn claim_integrator_fees(
+    deps: DepsMut,
+    info: &MessageInfo,
+    id: String,
+) -> Result<Response, ContractError> {
+    let Some(integrator) = load_integrator_optional(deps.storage, &id)? else {
+        return Err(ContractError::IntegratorNotFound { id });
+    };
+    if integrator.payout_address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let Some(payout) = integrator_payout_msg(deps.storage, &id, &integrator)? else {
+        return Err(ContractError::NoAccruedFees {});
+    };
+
+    Ok(Response::new().add_message(payout))
+}
+
+/// Clears the accrued fees of an integrator and returns the message paying them out.
+pub fn integrator_payout_msg(
+    storage: &mut dyn Storage,
+    id: &str,
+    integrator: &Integrator,
+) -> StdResult<Option<BankMsg>> {
+    let fees = load_integrator_fees(storage, id)?;
+    if fees.is_empty() {
+        return Ok(None);
+    }
+    clear_integrator_fees(storage, id)?;
+
+    Ok(Some(BankMsg::Send {
+        to_address: integrator.payout_address.to_string(),
+        amount: fees,
+    }))
 }
 
 pub fn handle_ibc_transfer_reply(deps: DepsMut, reply: Reply) -> Result<Response, ContractError> {
@@ -155,19 +959,60 @@ pub fn handle_ibc_transfer_reply(deps: DepsMut, reply: Reply) -> Result<Response
         &ibc_transfer_info,
     )?;
 
-    Ok(Response::new())
+    Ok(Response::new().add_event(events::ibc_sent(
+        &ibc_transfer_info,
+        ibc_transfer_response.sequence,
+    )))
+}
+
+pub fn handle_custom_call_reply(deps: DepsMut, reply: Reply) -> Result<Response, ContractError> {
+    let custom_call_info = load_custom_call_reply_state(deps.storage)?;
+
+    let SubMsgResult::Err(error) = reply.result else {
+        return Ok(Response::new());
+    };
+
+    Ok(Response::new()
+        .add_event(events::refund(
+            Some(custom_call_info.correlation_id),
+            &custom_call_info.local_fallback_address,
+            &custom_call_info.coin,
+            "custom_call_failed",
+        ))
+        .add_message(BankMsg::Send {
+            to_address: custom_call_info.local_fallback_address,
+            amount: vec![custom_call_info.coin],
+        })
+        .add_event(Event::new("custom_call_failed").add_attribute("error", error)))
 }
 
 pub fn handle_multiswap(
     deps: DepsMut,
     env: &Env,
+    info: &MessageInfo,
     mut swaps: Vec<MultiSwapMsg>,
     local_fallback_address: String,
+    integrator_id: Option<String>,
 ) -> Result<Response, ContractError> {
+    if load_pause_state(deps.storage)?.multi_swap {
+        return Err(ContractError::Paused {
+            operation: "multi-swap".to_owned(),
+        });
+    }
+
     if swaps.is_empty() {
         return Err(ContractError::InvalidAmountOfSwaps {});
     }
 
+    // the swaps are paid from the contract balance, which also holds the accrued fees
+    if sum_by_denom(swaps.iter().map(|swap| &swap.amount_in))? != sum_by_denom(&info.funds)? {
+        return Err(ContractError::InvalidMultiSwapFunds {});
+    }
+
+    if let Some(id) = &integrator_id {
+        ensure_integrator_exists(deps.as_ref(), id)?;
+    }
+
     // store multi-swap information
     swaps.reverse();
     store_multi_swap_state(
@@ -175,6 +1020,7 @@ pub fn handle_multiswap(
         &MultiSwapState {
             swaps,
             local_fallback_address,
+            integrator_id,
         },
     )?;
 
@@ -182,6 +1028,17 @@ pub fn handle_multiswap(
     handle_multiswap_reply(deps, env)
 }
 
+fn sum_by_denom<'a>(
+    coins: impl IntoIterator<Item = &'a Coin>,
+) -> StdResult<BTreeMap<&'a str, Uint128>> {
+    let mut totals: BTreeMap<&str, Uint128> = BTreeMap::new();
+    for coin in coins {
+        let total = totals.entry(&coin.denom).or_default();
+        *total = total.checked_add(coin.amount)?;
+    }
+    Ok(totals)
+}
+
 pub fn handle_multiswap_reply(deps: DepsMut, env: &Env) -> Result<Response, ContractError> {
     let mut multi_swaps = load_multi_swap_state(deps.storage)?;
     if multi_swaps.swaps.is_empty() {
@@ -197,6 +1054,8 @@ pub fn handle_multiswap_reply(deps: DepsMut, env: &Env) -> Result<Response, Cont
             swap_msg: next_swap.swap_msg,
             after_swap_action: next_swap.after_swap_action,
             local_fallback_address: multi_swaps.local_fallback_address.clone(),
+            integrator_id: multi_swaps.integrator_id.clone(),
+            refund_on_swap_failure: false,
         })?,
         funds: vec![next_swap.amount_in],
     };
@@ -210,54 +1069,756 @@ pub fn handle_multiswap_reply(deps: DepsMut, env: &Env) -> Result<Response, Cont
 
 pub fn estimate_price_impact_twap_min_input_output(
     deps: Deps,
-    env: &Env,
-    input_coin: Coin,
+    _env: &Env,
+    mut input_coin: Coin,
     to_coin_denom: String,
     pool_id: u64,
     max_price_impact: Decimal,
     twap_price: Decimal,
-) -> Result <PriceImpactTradeResponse, ContractError> {
+) -> Result<PriceImpactTradeResponse, ContractError> {
+    if twap_price.is_zero() {
+        return Err(ContractError::ZeroTwapPrice {});
+    }
 
-    // // Define your querier
     let poolmanager_querier = PoolmanagerQuerier::new(&deps.querier);
 
-    // Get the pool based on ID
-    let pool = poolmanager_querier.pool(pool_id)?;
-
-    let spot_price_response = poolmanager_querier.spot_price(pool_id, input_coin.denom.clone(), to_coin_denom.clone())?;
-    let spot_price_str = spot_price_response.spot_price; // Assuming spot_price is a string in the response
-    let spot_price = Decimal::from_str(&spot_price_str).map_err(|_| ContractError::InvalidSpotPrice)?; 
+    let spot_price_response =
+        poolmanager_querier.spot_price(pool_id, input_coin.denom.clone(), to_coin_denom.clone())?;
+    let spot_price = Decimal::from_str(&spot_price_response.spot_price)
+        .map_err(|_| ContractError::InvalidSpotPrice {})?;
 
     // Calculate adjusted maxPriceImpact based on twapPrice and spotPrice
-    let price_deviation = (spot_price - twap_price) / twap_price;
-    max_price_impact = max_price_impact - price_deviation;
+    let max_price_impact =
+        max_price_impact.saturating_sub(get_price_deviation(spot_price, twap_price)?);
 
     loop {
+        if input_coin.amount.is_zero() {
+            return Err(ContractError::ZeroTokenOut {});
+        }
+
         // Calculate token out
-        let estimate_response = poolmanager_querier.estimate_single_pool_swap_exact_amount_in(pool_id, input_coin.denom.clone(), to_coin_denom.clone())?;
-        let token_out = estimate_response.token_out_amount; // Assuming token_out is a field in the response
+        let estimate_response = poolmanager_querier.estimate_single_pool_swap_exact_amount_in(
+            pool_id,
+            input_coin.to_string(),
+            to_coin_denom.clone(),
+        )?;
+        let token_out = Uint128::from_str(&estimate_response.token_out_amount)?;
 
         // If token_out is zero, return an error
-        if token_out.amount.is_zero() {
-            return Err(ContractError::ZeroTokenOut);
+        if token_out.is_zero() {
+            return Err(ContractError::ZeroTokenOut {});
         }
 
-        let curr_trade_price = token_out.amount / input_coin.amount;
-        let price_deviation = (spot_price - curr_trade_price) / curr_trade_price;
-
-        if price_deviation <= max_price_impact {
-            return Ok(PriceImpactTradeResponse{
-                amount_in: input_coin.clone(),
-                amount_out: token_out,
+        let curr_trade_price = Decimal::from_ratio(token_out, input_coin.amount);
+        if get_price_deviation(spot_price, curr_trade_price)? <= max_price_impact {
+            return Ok(PriceImpactTradeResponse {
+                amount_in: input_coin,
+                amount_out: Coin {
+                    denom: to_coin_denom,
+                    amount: token_out,
+                },
             });
-        } else {
-            // Half the input amount and try again
-            input_coin.amount = input_coin.amount / Uint128::from(2u64);
         }
+
+        // Half the input amount and try again
+        input_coin.amount /= Uint128::from(2u64);
     }
+}
+
+fn get_price_deviation(spot_price: Decimal, price: Decimal) -> StdResult<Decimal> {
+    let diff = if spot_price > price {
+        spot_price - price
+    } else {
+        price - spot_price
+    };
+
+    diff.checked_div(price)
+        .map_err(|e| StdError::generic_err(e.to_string()))
+}
 
-    // Ok(PriceImpactTradeResponse{
-    //     amount_in: input_coin.clone(),
-    //     amount_out: input_coin,
-    // })
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+        MsgSwapExactAmountOut, MsgSwapExactAmountOutResponse, SwapAmountInRoute,
+    };
+
+    use super::*;
+    use crate::{
+        msg::AxelarFee,
+        state::{
+            load_accrued_fees, peek_awaiting_ibc_transfer_optional, store_config,
+            store_pause_state, AxelarConfig, Config, PauseState,
+        },
+    };
+
+    fn split_action(share: SplitShare) -> SplitAction {
+        SplitAction {
+            share,
+            action: AfterSwapAction::BankSend {
+                receiver: "receiver".to_owned(),
+            },
+        }
+    }
+
+    fn weight(weight: u64) -> SplitAction {
+        split_action(SplitShare::Weight(weight))
+    }
+
+    fn amount(amount: u128) -> SplitAction {
+        split_action(SplitShare::Amount(Uint128::new(amount)))
+    }
+
+    fn amounts(amounts: &[u128]) -> Vec<Uint128> {
+        amounts.iter().map(|amount| Uint128::new(*amount)).collect()
+    }
+
+    #[test]
+    fn split_output_by_weights_gives_dust_to_last_weight() {
+        let (split, remainder) =
+            split_output(Uint128::new(100), &[weight(1), weight(1), weight(1)]).unwrap();
+
+        assert_eq!(split, amounts(&[33, 33, 34]));
+        assert_eq!(remainder, Uint128::zero());
+    }
+
+    #[test]
+    fn split_output_by_fixed_amounts_returns_remainder() {
+        let (split, remainder) =
+            split_output(Uint128::new(100), &[amount(30), amount(50)]).unwrap();
+
+        assert_eq!(split, amounts(&[30, 50]));
+        assert_eq!(remainder, Uint128::new(20));
+    }
+
+    #[test]
+    fn split_output_pays_fixed_amounts_before_weights() {
+        let (split, remainder) = split_output(
+            Uint128::new(101),
+            &[weight(1), amount(40), weight(2), amount(1)],
+        )
+        .unwrap();
+
+        // 60 left for the weights, the dust goes to the last weighted action
+        assert_eq!(split, amounts(&[20, 40, 40, 1]));
+        assert_eq!(remainder, Uint128::zero());
+    }
+
+    #[test]
+    fn split_output_rejects_fixed_amounts_above_output() {
+        let err =
+            split_output(Uint128::new(100), &[amount(60), amount(41), weight(1)]).unwrap_err();
+
+        assert!(matches!(err, ContractError::InvalidSplit { .. }));
+    }
+
+    #[test]
+    fn split_output_rejects_overflowing_fixed_amounts() {
+        let err = split_output(Uint128::new(100), &[amount(u128::MAX), amount(1)]).unwrap_err();
+
+        assert!(matches!(err, ContractError::Std(StdError::Overflow { .. })));
+    }
+
+    #[test]
+    fn split_output_leaves_small_weighted_shares_empty() {
+        // zero amounts are skipped when the split is dispatched
+        let (split, remainder) = split_output(Uint128::new(1), &[weight(1), weight(1)]).unwrap();
+
+        assert_eq!(split, amounts(&[0, 1]));
+        assert_eq!(remainder, Uint128::zero());
+    }
+
+    #[test]
+    fn validate_split_rejects_zero_shares() {
+        for actions in [vec![weight(0)], vec![amount(0), weight(1)]] {
+            let err = validate_split(&actions).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidSplit { .. }));
+        }
+    }
+
+    #[test]
+    fn validate_split_rejects_empty_split() {
+        let err = validate_split(&[]).unwrap_err();
+
+        assert!(matches!(err, ContractError::InvalidSplit { .. }));
+    }
+
+    fn multi_swap(amount_in: Coin) -> MultiSwapMsg {
+        MultiSwapMsg {
+            amount_in,
+            swap_msg: OsmosisSwapMsg {
+                token_out_min_amount: Some("1".to_owned()),
+                path: vec![],
+                split_routes: vec![],
+                twap_slippage: None,
+            },
+            after_swap_action: AfterSwapAction::BankSend {
+                receiver: "receiver".to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn multiswap_rejects_funds_not_matching_swap_inputs() {
+        let swaps = vec![
+            multi_swap(Coin::new(60, "uosmo")),
+            multi_swap(Coin::new(40, "uosmo")),
+        ];
+
+        for funds in [
+            vec![],
+            vec![Coin::new(99, "uosmo")],
+            vec![Coin::new(100, "uatom")],
+        ] {
+            let mut deps = mock_dependencies();
+            let err = handle_multiswap(
+                deps.as_mut(),
+                &mock_env(),
+                &mock_info("sender", &funds),
+                swaps.clone(),
+                "fallback".to_owned(),
+                None,
+            )
+            .unwrap_err();
+
+            assert!(matches!(err, ContractError::InvalidMultiSwapFunds {}));
+        }
+    }
+
+    #[test]
+    fn multiswap_accepts_funds_matching_swap_inputs() {
+        let mut deps = mock_dependencies();
+        let response = handle_multiswap(
+            deps.as_mut(),
+            &mock_env(),
+            &mock_info("sender", &[Coin::new(100, "uosmo")]),
+            vec![
+                multi_swap(Coin::new(60, "uosmo")),
+                multi_swap(Coin::new(40, "uosmo")),
+            ],
+            "fallback".to_owned(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(response.messages.len(), 1);
+    }
+
+    #[test]
+    fn price_impact_estimate_rejects_zero_twap_price() {
+        let deps = mock_dependencies();
+        let err = estimate_price_impact_twap_min_input_output(
+            deps.as_ref(),
+            &mock_env(),
+            Coin::new(100, "uosmo"),
+            "uatom".to_owned(),
+            1,
+            Decimal::percent(1),
+            Decimal::zero(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::ZeroTwapPrice {}));
+    }
+
+    #[test]
+    fn price_deviation_rejects_zero_price() {
+        assert!(get_price_deviation(Decimal::one(), Decimal::zero()).is_err());
+        assert_eq!(
+            get_price_deviation(Decimal::one(), Decimal::percent(50)).unwrap(),
+            Decimal::one()
+        );
+    }
+
+    fn store_protocol_fee(storage: &mut dyn Storage, protocol_fee_bps: u16) {
+        let config = Config {
+            ibc_packet_lifetime: 60,
+            guardian: None,
+            protocol_fee_bps,
+            axelar: None,
+        };
+        store_config(storage, &config).unwrap();
+    }
+
+    #[test]
+    fn exact_amount_out_swap_accrues_protocol_fee_on_top() {
+        let mut deps = mock_dependencies();
+        store_protocol_fee(deps.as_mut().storage, 100);
+
+        let response = swap_exact_amount_out(
+            deps.as_mut(),
+            &mock_env(),
+            &mock_info("sender", &[Coin::new(1000, "uosmo")]),
+            OsmosisSwapExactAmountOutMsg {
+                token_out_amount: "990".to_owned(),
+                token_in_max_amount: "1000".to_owned(),
+                path: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uatom".to_owned(),
+                }],
+            },
+            AfterSwapAction::BankSend {
+                receiver: "receiver".to_owned(),
+            },
+            "fallback".to_owned(),
+            false,
+        )
+        .unwrap();
+
+        let CosmosMsg::Stargate { value, .. } = &response.messages[0].msg else {
+            panic!("unexpected message: {:?}", response.messages[0].msg);
+        };
+        let swap = MsgSwapExactAmountOut::decode(value.as_slice()).unwrap();
+        assert_eq!(swap.token_out.unwrap().amount, "1000");
+
+        let reply = Reply {
+            id: MsgReplyId::Swap.repr(),
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(
+                    MsgSwapExactAmountOutResponse {
+                        token_in_amount: "900".to_owned(),
+                    }
+                    .into(),
+                ),
+            }),
+        };
+        let response = handle_after_swap_action(deps.as_mut(), &mock_env(), reply).unwrap();
+
+        assert_eq!(
+            load_accrued_fees(deps.as_ref().storage).unwrap(),
+            vec![Coin::new(10, "uatom")]
+        );
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "receiver".to_owned(),
+                amount: vec![Coin::new(990, "uatom")],
+            })
+        );
+        assert_eq!(
+            response.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fallback".to_owned(),
+                amount: vec![Coin::new(100, "uosmo")],
+            })
+        );
+    }
+
+    #[test]
+    fn add_fee_leaves_at_least_the_amount_after_the_fee() {
+        for (amount, fee_bps) in [(990, 100), (1, 30), (12_345, 9_999), (1_000, 0)] {
+            let mut coin = Coin::new(
+                add_fee(Uint128::new(amount), fee_bps).unwrap().u128(),
+                "uatom",
+            );
+            deduct_fee(&mut coin, fee_bps);
+            assert!(coin.amount >= Uint128::new(amount));
+        }
+    }
+
+    /// Memos of the ibc transfers built for `action` on a swap output of `amount` uosmo.
+    fn transfer_memos(deps: DepsMut, action: AfterSwapAction, amount: u128) -> Vec<String> {
+        build_after_swap_action_msgs(
+            deps,
+            &mock_env(),
+            action,
+            Coin::new(amount, "uosmo"),
+            "fallback",
+            1,
+        )
+        .unwrap()
+        .into_iter()
+        .filter_map(|msg| match msg.msg {
+            CosmosMsg::Stargate { value, .. } => {
+                Some(MsgTransfer::decode(value.as_slice()).unwrap().memo)
+            }
+            _ => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn remote_squid_memo_leaves_out_unset_fields() {
+        let mut deps = mock_dependencies();
+        store_protocol_fee(deps.as_mut().storage, 0);
+
+        let msg = ExecuteMsg::SwapWithAction {
+            swap_msg: OsmosisSwapMsg {
+                token_out_min_amount: Some("1".to_owned()),
+                path: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "ujuno".to_owned(),
+                }],
+                split_routes: vec![],
+                twap_slippage: None,
+            },
+            after_swap_action: AfterSwapAction::IbcTransfer {
+                receiver: "cosmos1receiver".to_owned(),
+                channel: "channel-1".to_owned(),
+                next_memo: None,
+                timeout_timestamp: None,
+                timeout_height: None,
+                forward_hops: vec![],
+            },
+            local_fallback_address: "juno1fallback".to_owned(),
+            integrator_id: None,
+            refund_on_swap_failure: false,
+        };
+        let action = AfterSwapAction::RemoteSquidCall {
+            channel: "channel-0".to_owned(),
+            contract_address: "juno1squid".to_owned(),
+            msg: Box::new(msg),
+            timeout_timestamp: None,
+            timeout_height: None,
+        };
+
+        assert_eq!(
+            transfer_memos(deps.as_mut(), action, 100),
+            vec![concat!(
+                r#"{"wasm":{"contract":"juno1squid","msg":{"swap_with_action":{"#,
+                r#""swap_msg":{"token_out_min_amount":"1","path":[{"pool_id":"1","token_out_denom":"ujuno"}]},"#,
+                r#""after_swap_action":{"ibc_transfer":{"receiver":"cosmos1receiver","channel":"channel-1","next_memo":null}},"#,
+                r#""local_fallback_address":"juno1fallback"}}},"ibc_callback":"cosmos2contract"}"#,
+            )]
+        );
+    }
+
+    #[test]
+    fn ibc_timeout_uses_requested_timestamp_in_the_future() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let timeout =
+            |timestamp| get_ibc_timeout(&deps.storage, &env, "channel-0", timestamp, None);
+
+        let in_a_minute = env.block.time.plus_seconds(60);
+        let (height, timestamp) = timeout(Some(in_a_minute)).unwrap();
+        assert_eq!((height, timestamp), (None, Some(in_a_minute.nanos())));
+
+        let err = timeout(Some(env.block.time)).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidIbcTimeout { .. }));
+    }
+
+    fn ibc_transfer(next_memo: Option<&str>, forward_hops: Vec<ForwardHop>) -> AfterSwapAction {
+        AfterSwapAction::IbcTransfer {
+            receiver: "cosmos1receiver".to_owned(),
+            channel: "channel-0".to_owned(),
+            next_memo: next_memo.map(|memo| serde_json_wasm::from_str(memo).unwrap()),
+            timeout_timestamp: None,
+            timeout_height: None,
+            forward_hops,
+        }
+    }
+
+    #[test]
+    fn ibc_transfer_rejects_memo_that_is_not_an_object() {
+        for memo in [r#""x""#, "[]", "1"] {
+            let err = validate_after_swap_action(&ibc_transfer(Some(memo), vec![])).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidMemo {}));
+
+            let mut deps = mock_dependencies();
+            store_protocol_fee(deps.as_mut().storage, 0);
+            let err = build_after_swap_action_msgs(
+                deps.as_mut(),
+                &mock_env(),
+                ibc_transfer(Some(memo), vec![]),
+                Coin::new(100, "uosmo"),
+                "fallback",
+                1,
+            )
+            .unwrap_err();
+            assert!(matches!(err, ContractError::InvalidMemo {}));
+        }
+
+        assert!(validate_after_swap_action(&ibc_transfer(Some(r#"{"a":1}"#), vec![])).is_ok());
+    }
+
+    #[test]
+    fn split_ibc_transfers_are_awaited_with_their_own_amounts() {
+        let mut deps = mock_dependencies();
+        store_protocol_fee(deps.as_mut().storage, 0);
+
+        let action = AfterSwapAction::Split {
+            actions: vec![
+                SplitAction {
+                    share: SplitShare::Weight(1),
+                    action: ibc_transfer(None, vec![]),
+                },
+                SplitAction {
+                    share: SplitShare::Weight(3),
+                    action: ibc_transfer(None, vec![]),
+                },
+            ],
+        };
+        let msgs = build_after_swap_action_msgs(
+            deps.as_mut(),
+            &mock_env(),
+            action,
+            Coin::new(100, "uosmo"),
+            "fallback",
+            1,
+        )
+        .unwrap();
+
+        let transferred: Vec<_> = msgs
+            .iter()
+            .map(|msg| match &msg.msg {
+                CosmosMsg::Stargate { value, .. } => {
+                    MsgTransfer::decode(value.as_slice())
+                        .unwrap()
+                        .token
+                        .unwrap()
+                        .amount
+                }
+                msg => panic!("unexpected message: {msg:?}"),
+            })
+            .collect();
+        assert_eq!(transferred, vec!["25", "75"]);
+
+        // the replies arrive in dispatch order
+        for sequence in [1, 2] {
+            let reply = Reply {
+                id: MsgReplyId::IbcTransfer.repr(),
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(MsgTransferResponse { sequence }.encode_to_vec().into()),
+                }),
+            };
+            handle_ibc_transfer_reply(deps.as_mut(), reply).unwrap();
+        }
+
+        let awaiting_amount = |sequence| {
+            peek_awaiting_ibc_transfer_optional(deps.as_ref().storage, "channel-0", sequence)
+                .unwrap()
+                .unwrap()
+                .amount
+        };
+        assert_eq!(awaiting_amount(1), Uint128::new(25));
+        assert_eq!(awaiting_amount(2), Uint128::new(75));
+    }
+
+    fn store_axelar_config(storage: &mut dyn Storage) {
+        let config = Config {
+            ibc_packet_lifetime: 60,
+            guardian: None,
+            protocol_fee_bps: 0,
+            axelar: Some(AxelarConfig {
+                channel: "channel-208".to_owned(),
+                gateway_address: "axelar1gateway".to_owned(),
+            }),
+        };
+        store_config(storage, &config).unwrap();
+    }
+
+    fn axelar_gmp(message_type: GmpMessageType, fee: Option<u128>) -> AfterSwapAction {
+        AfterSwapAction::AxelarGmp {
+            destination_chain: "ethereum".to_owned(),
+            destination_address: "0xdestination".to_owned(),
+            payload: Binary::from(vec![1, 2, 255]),
+            message_type,
+            fee: fee.map(|amount| AxelarFee {
+                amount: Uint128::new(amount),
+                recipient: "axelar1fee".to_owned(),
+            }),
+        }
+    }
+
+    #[test]
+    fn axelar_gmp_message_memo() {
+        let mut deps = mock_dependencies();
+        store_axelar_config(deps.as_mut().storage);
+
+        let msgs = build_after_swap_action_msgs(
+            deps.as_mut(),
+            &mock_env(),
+            axelar_gmp(GmpMessageType::Message, None),
+            Coin::new(100, "uosmo"),
+            "fallback",
+            1,
+        )
+        .unwrap();
+
+        let CosmosMsg::Stargate { value, .. } = &msgs[0].msg else {
+            panic!("unexpected message: {:?}", msgs[0].msg);
+        };
+        let transfer = MsgTransfer::decode(value.as_slice()).unwrap();
+        assert_eq!(transfer.source_channel, "channel-208");
+        assert_eq!(transfer.receiver, "axelar1gateway");
+        assert_eq!(
+            transfer.memo,
+            concat!(
+                r#"{"destination_chain":"ethereum","destination_address":"0xdestination","#,
+                r#""payload":[1,2,255],"type":1,"ibc_callback":"cosmos2contract"}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn axelar_gmp_message_with_token_memo_carries_fee() {
+        let mut deps = mock_dependencies();
+        store_axelar_config(deps.as_mut().storage);
+
+        assert_eq!(
+            transfer_memos(
+                deps.as_mut(),
+                axelar_gmp(GmpMessageType::MessageWithToken, Some(10)),
+                100
+            ),
+            vec![concat!(
+                r#"{"destination_chain":"ethereum","destination_address":"0xdestination","#,
+                r#""payload":[1,2,255],"type":2,"fee":{"amount":"10","recipient":"axelar1fee"},"#,
+                r#""ibc_callback":"cosmos2contract"}"#,
+            )]
+        );
+    }
+
+    #[test]
+    fn axelar_gmp_fee_has_to_be_below_output() {
+        let mut deps = mock_dependencies();
+        store_axelar_config(deps.as_mut().storage);
+
+        for fee in [100, 101] {
+            let err = build_after_swap_action_msgs(
+                deps.as_mut(),
+                &mock_env(),
+                axelar_gmp(GmpMessageType::MessageWithToken, Some(fee)),
+                Coin::new(100, "uosmo"),
+                "fallback",
+                1,
+            )
+            .unwrap_err();
+            assert!(matches!(err, ContractError::InvalidAxelarGmp { .. }));
+        }
+
+        let memos = transfer_memos(
+            deps.as_mut(),
+            axelar_gmp(GmpMessageType::MessageWithToken, Some(99)),
+            100,
+        );
+        assert_eq!(memos.len(), 1);
+    }
+
+    #[test]
+    fn forward_memo_nests_hops_in_order_with_the_next_memo_innermost() {
+        let mut deps = mock_dependencies();
+        store_protocol_fee(deps.as_mut().storage, 0);
+
+        let hops = vec![
+            ForwardHop {
+                channel: "channel-1".to_owned(),
+                receiver: "juno1hop".to_owned(),
+                timeout_seconds: Some(600),
+                retries: Some(2),
+            },
+            ForwardHop {
+                channel: "channel-2".to_owned(),
+                receiver: "stars1receiver".to_owned(),
+                timeout_seconds: None,
+                retries: None,
+            },
+        ];
+        let action = ibc_transfer(Some(r#"{"wasm":{"contract":"stars1contract"}}"#), hops);
+
+        // keys are sorted, the callback stays on the outermost object read by this chain
+        assert_eq!(
+            transfer_memos(deps.as_mut(), action, 100),
+            vec![concat!(
+                r#"{"forward":{"channel":"channel-1","next":"#,
+                r#"{"forward":{"channel":"channel-2","next":{"wasm":{"contract":"stars1contract"}},"#,
+                r#""port":"transfer","receiver":"stars1receiver"}},"#,
+                r#""port":"transfer","receiver":"juno1hop","retries":2,"timeout":600000000000},"#,
+                r#""ibc_callback":"cosmos2contract"}"#,
+            )]
+        );
+    }
+
+    /// Runs each operation that can be paused on a fresh contract with `pause_state`.
+    fn paused_operations(pause_state: &PauseState) -> Vec<bool> {
+        let deps = || {
+            let mut deps = mock_dependencies();
+            store_protocol_fee(deps.as_mut().storage, 0);
+            store_pause_state(deps.as_mut().storage, pause_state).unwrap();
+            deps
+        };
+        let funds = [Coin::new(100, "uosmo")];
+
+        let swap = swap_exact_amount_out(
+            deps().as_mut(),
+            &mock_env(),
+            &mock_info("sender", &funds),
+            OsmosisSwapExactAmountOutMsg {
+                token_out_amount: "90".to_owned(),
+                token_in_max_amount: "100".to_owned(),
+                path: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uatom".to_owned(),
+                }],
+            },
+            AfterSwapAction::BankSend {
+                receiver: "receiver".to_owned(),
+            },
+            "fallback".to_owned(),
+            false,
+        )
+        .map(|_| ());
+        let multi_swap = handle_multiswap(
+            deps().as_mut(),
+            &mock_env(),
+            &mock_info("sender", &funds),
+            vec![multi_swap(funds[0].clone())],
+            "fallback".to_owned(),
+            None,
+        )
+        .map(|_| ());
+        let ibc_transfer = build_after_swap_action_msgs(
+            deps().as_mut(),
+            &mock_env(),
+            ibc_transfer(None, vec![]),
+            funds[0].clone(),
+            "fallback",
+            1,
+        )
+        .map(|_| ());
+
+        [swap, multi_swap, ibc_transfer]
+            .into_iter()
+            .map(|result| match result {
+                Ok(()) => false,
+                Err(ContractError::Paused { .. }) => true,
+                Err(err) => panic!("unexpected error: {err:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pause_flags_only_block_their_own_operation() {
+        let pause_states = [
+            (PauseState::default(), vec![false, false, false]),
+            (
+                PauseState {
+                    swaps: true,
+                    ..Default::default()
+                },
+                vec![true, false, false],
+            ),
+            (
+                PauseState {
+                    multi_swap: true,
+                    ..Default::default()
+                },
+                vec![false, true, false],
+            ),
+            (
+                PauseState {
+                    ibc_transfers: true,
+                    ..Default::default()
+                },
+                vec![false, false, true],
+            ),
+        ];
+
+        for (pause_state, paused) in pause_states {
+            assert_eq!(paused_operations(&pause_state), paused, "{pause_state:?}");
+        }
+    }
+}
@@ -1,31 +1,96 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
 };
-// use cw2::set_contract_version;
+use cw2::{set_contract_version, CONTRACT};
+use osmosis_router::{TwapParams, DEFAULT_TWAP_WINDOW};
 
+use crate::admin;
 use crate::commands::{self};
 use crate::error::ContractError;
 use crate::ibc;
-use crate::msg::{ExecuteMsg, IBCLifecycleComplete, InstantiateMsg, MsgReplyId, QueryMsg, SudoMsg};
+use crate::migrations;
+use crate::msg::{
+    ExecuteMsg, IBCLifecycleComplete, InstantiateMsg, MigrateMsg, MsgReplyId, QueryMsg, SudoMsg,
+};
+use crate::queries;
+use crate::state::{store_config, store_ownership, Config, Ownership};
 
-/*
 // version info for migration info
-const CONTRACT_NAME: &str = "crates.io:testme";
+const CONTRACT_NAME: &str = concat!("crates.io:", env!("CARGO_PKG_NAME"));
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-*/
+
+const DEFAULT_IBC_PACKET_LIFETIME: u64 = 604_800u64;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    let owner = match msg.owner {
+        Some(owner) => deps.api.addr_validate(&owner)?,
+        None => info.sender,
+    };
+
+    let config = Config {
+        ibc_packet_lifetime: msg
+            .ibc_packet_lifetime
+            .unwrap_or(DEFAULT_IBC_PACKET_LIFETIME),
+        guardian: msg
+            .guardian
+            .map(|guardian| deps.api.addr_validate(&guardian))
+            .transpose()?,
+        protocol_fee_bps: msg.protocol_fee_bps.unwrap_or_default(),
+        axelar: msg.axelar,
+    };
+    admin::validate_config(&config)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    store_config(deps.storage, &config)?;
+    store_ownership(
+        deps.storage,
+        &Ownership {
+            owner,
+            pending_owner: None,
+        },
+    )?;
+
     Ok(Response::default())
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = CONTRACT.may_load(deps.storage)?;
+    migrations::ensure_upgrade(stored.as_ref(), CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    if stored.is_none() {
+        migrations::migrate_from_unversioned(
+            deps.storage,
+            deps.api,
+            msg,
+            Config {
+                ibc_packet_lifetime: DEFAULT_IBC_PACKET_LIFETIME,
+                guardian: None,
+                protocol_fee_bps: 0,
+                axelar: None,
+            },
+        )?;
+    }
+    migrations::index_awaiting_ibc_transfers(deps.storage, stored.as_ref())?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute(
+            "from_version",
+            stored.map_or_else(|| "unversioned".to_owned(), |v| v.version),
+        )
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -38,6 +103,8 @@ pub fn execute(
             swap_msg,
             after_swap_action,
             local_fallback_address,
+            integrator_id,
+            refund_on_swap_failure,
         } => commands::swap(
             deps,
             &env,
@@ -45,11 +112,100 @@ pub fn execute(
             swap_msg,
             after_swap_action,
             local_fallback_address,
+            integrator_id,
+            refund_on_swap_failure,
+        ),
+        ExecuteMsg::SwapExactAmountOutWithAction {
+            swap_msg,
+            after_swap_action,
+            local_fallback_address,
+            refund_on_swap_failure,
+        } => commands::swap_exact_amount_out(
+            deps,
+            &env,
+            &info,
+            swap_msg,
+            after_swap_action,
+            local_fallback_address,
+            refund_on_swap_failure,
         ),
         ExecuteMsg::MultiSwap {
             swaps,
             local_fallback_address,
-        } => commands::handle_multiswap(deps, &env, swaps, local_fallback_address),
+            integrator_id,
+        } => commands::handle_multiswap(
+            deps,
+            &env,
+            &info,
+            swaps,
+            local_fallback_address,
+            integrator_id,
+        ),
+        ExecuteMsg::SwapWithRegisteredRoute {
+            output_denom,
+            token_out_min_amount,
+            twap_slippage,
+            after_swap_action,
+            local_fallback_address,
+            refund_on_swap_failure,
+        } => commands::swap_with_registered_route(
+            deps,
+            &env,
+            &info,
+            output_denom,
+            token_out_min_amount,
+            twap_slippage,
+            after_swap_action,
+            local_fallback_address,
+            refund_on_swap_failure,
+        ),
+        ExecuteMsg::AxelarGmpSwap {
+            source_chain,
+            source_address,
+            payload,
+        } => commands::axelar_gmp_swap(deps, &env, &info, source_chain, source_address, payload),
+        ExecuteMsg::SetRoute {
+            input_denom,
+            output_denom,
+            path,
+        } => admin::set_route(deps, &info, input_denom, output_denom, path),
+        ExecuteMsg::RemoveRoute {
+            input_denom,
+            output_denom,
+        } => admin::remove_route(deps, &info, input_denom, output_denom),
+        ExecuteMsg::UpdateConfig {
+            ibc_packet_lifetime,
+            protocol_fee_bps,
+            axelar,
+        } => admin::update_config(deps, &info, ibc_packet_lifetime, protocol_fee_bps, axelar),
+        ExecuteMsg::TransferOwnership { new_owner } => {
+            admin::transfer_ownership(deps, &info, new_owner)
+        }
+        ExecuteMsg::AcceptOwnership {} => admin::accept_ownership(deps, &info),
+        ExecuteMsg::SetGuardian { guardian } => admin::set_guardian(deps, &info, guardian),
+        ExecuteMsg::SetPauseState {
+            swaps,
+            multi_swap,
+            ibc_transfers,
+        } => admin::set_pause_state(deps, &info, swaps, multi_swap, ibc_transfers),
+        ExecuteMsg::WithdrawFees { recipient } => admin::withdraw_fees(deps, &info, recipient),
+        ExecuteMsg::SetChannelPacketLifetime {
+            channel,
+            packet_lifetime,
+        } => admin::set_channel_packet_lifetime(deps, &info, channel, packet_lifetime),
+        ExecuteMsg::SetIntegrator {
+            id,
+            fee_bps,
+            payout_address,
+        } => admin::set_integrator(deps, &info, id, fee_bps, payout_address),
+        ExecuteMsg::RemoveIntegrator { id } => admin::remove_integrator(deps, &info, id),
+        ExecuteMsg::ClaimIntegratorFees { id } => commands::claim_integrator_fees(deps, &info, id),
+        ExecuteMsg::ReclaimIbcTransfer { channel, sequence } => {
+            ibc::reclaim_ibc_transfer(deps, &env, &info, channel, sequence)
+        }
+        ExecuteMsg::DropIbcTransfer { channel, sequence } => {
+            ibc::drop_ibc_transfer(deps, &info, channel, sequence)
+        }
     }
 }
 
@@ -59,6 +215,7 @@ pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, Contract
         Some(MsgReplyId::Swap) => commands::handle_after_swap_action(deps, &env, reply),
         Some(MsgReplyId::IbcTransfer) => commands::handle_ibc_transfer_reply(deps, reply),
         Some(MsgReplyId::MultiSwap) => commands::handle_multiswap_reply(deps, &env),
+        Some(MsgReplyId::CustomCall) => commands::handle_custom_call_reply(deps, reply),
         None => Err(ContractError::InvalidReplyId {}),
     }
 }
@@ -85,12 +242,83 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             input_coin,
             path,
             slippage,
+            twap_type,
+            twap_window_seconds,
         } => to_binary(
             &osmosis_router::router::estimate_min_twap_output(
-                deps, &env, input_coin, path, slippage,
+                deps,
+                &env,
+                input_coin,
+                path,
+                slippage,
+                TwapParams {
+                    twap_type: twap_type.unwrap_or_default(),
+                    window_seconds: twap_window_seconds.unwrap_or(DEFAULT_TWAP_WINDOW),
+                },
+            )
+            .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::FindBestRoute {
+            input_coin,
+            output_denom,
+            pool_ids,
+            max_hops,
+        } => to_binary(
+            &osmosis_router::router::find_best_route(
+                deps,
+                input_coin,
+                output_denom,
+                pool_ids,
+                max_hops,
             )
-            .unwrap(),
+            .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::Route {
+            input_denom,
+            output_denom,
+        } => to_binary(
+            &queries::query_route(deps, input_denom, output_denom)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::Routes { start_after, limit } => {
+            to_binary(&queries::query_routes(deps, start_after, limit)?)
+        }
+        QueryMsg::Config {} => to_binary(&queries::query_config(deps)?),
+        QueryMsg::Ownership {} => to_binary(&queries::query_ownership(deps)?),
+        QueryMsg::PauseState {} => to_binary(&queries::query_pause_state(deps)?),
+        QueryMsg::AccruedFees {} => to_binary(&queries::query_accrued_fees(deps)?),
+        QueryMsg::ChannelPacketLifetime { channel } => {
+            to_binary(&queries::query_channel_packet_lifetime(deps, channel)?)
+        }
+        QueryMsg::Integrator { id } => to_binary(
+            &queries::query_integrator(deps, id)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
         ),
+        QueryMsg::IntegratorFees { id } => to_binary(&queries::query_integrator_fees(deps, id)?),
+        QueryMsg::AwaitingIbcTransfer { channel, sequence } => to_binary(
+            &queries::query_awaiting_ibc_transfer(deps, channel, sequence)
+                .map_err(|e| StdError::generic_err(e.to_string()))?,
+        ),
+        QueryMsg::AwaitingIbcTransfersByChannel {
+            channel,
+            start_after,
+            limit,
+        } => to_binary(&queries::query_awaiting_ibc_transfers_by_channel(
+            deps,
+            channel,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::AwaitingIbcTransfersByFallbackAddress {
+            local_fallback_address,
+            start_after,
+            limit,
+        } => to_binary(&queries::query_awaiting_ibc_transfers_by_fallback_address(
+            deps,
+            local_fallback_address,
+            start_after,
+            limit,
+        )?),
         QueryMsg::EstimatePriceImpactTwapMinInputOutput {
             input_coin,
             to_coin_denom,
@@ -101,7 +329,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             &commands::estimate_price_impact_twap_min_input_output(
                 deps, &env, input_coin, to_coin_denom, pool_id, max_price_impact, twap_price,
             )
-            .unwrap(),
+            .map_err(|e| StdError::generic_err(e.to_string()))?,
         ),
     }
 }
@@ -1,4 +1,5 @@
 use cosmwasm_std::StdError;
+use multicall::error::MulticallError;
 use osmosis_router::error::OsmosisRouterError;
 use thiserror::Error;
 
@@ -10,6 +11,9 @@ pub enum ContractError {
     #[error("{0}")]
     OsmosisRouterError(#[from] OsmosisRouterError),
 
+    #[error("{0}")]
+    MulticallError(#[from] MulticallError),
+
     #[error("{0}")]
     PaymentError(#[from] cw_utils::PaymentError),
 
@@ -28,6 +32,66 @@ pub enum ContractError {
     #[error("Invalid amount of multi-swap calls. Must be non-zero")]
     InvalidAmountOfSwaps {},
 
+    #[error("Multi-swap funds must match the sum of the swap inputs")]
+    InvalidMultiSwapFunds {},
+
     #[error("contract locked: {msg}")]
     ContractLocked { msg: String },
+
+    #[error("{operation} is paused")]
+    Paused { operation: String },
+
+    #[error("No fees accrued")]
+    NoAccruedFees {},
+
+    #[error("Awaiting ibc transfer {sequence} on {channel} not found")]
+    AwaitingIbcTransferNotFound { channel: String, sequence: u64 },
+
+    #[error("Cannot reclaim ibc transfer: {msg}")]
+    InvalidIbcReclaim { msg: String },
+
+    #[error("Integrator {id} not found")]
+    IntegratorNotFound { id: String },
+
+    #[error("Invalid migration: {msg}")]
+    InvalidMigration { msg: String },
+
+    #[error("Invalid ibc timeout: {msg}")]
+    InvalidIbcTimeout { msg: String },
+
+    #[error("Axelar is not configured")]
+    AxelarNotConfigured {},
+
+    #[error("Invalid Axelar GMP call: {msg}")]
+    InvalidAxelarGmp { msg: String },
+
+    #[error("Invalid ABI payload: {msg}")]
+    InvalidAbiPayload { msg: String },
+
+    #[error("Invalid forward hop: {msg}")]
+    InvalidForwardHop { msg: String },
+
+    #[error("Invalid remote squid call: {msg}")]
+    InvalidRemoteSquidCall { msg: String },
+
+    #[error("Invalid split: {msg}")]
+    InvalidSplit { msg: String },
+
+    #[error("Invalid config: {msg}")]
+    InvalidConfig { msg: String },
+
+    #[error("Route from {input_denom} to {output_denom} not found")]
+    RouteNotFound {
+        input_denom: String,
+        output_denom: String,
+    },
+
+    #[error("Invalid spot price")]
+    InvalidSpotPrice {},
+
+    #[error("TWAP price must be non-zero")]
+    ZeroTwapPrice {},
+
+    #[error("Swap estimate returned zero output")]
+    ZeroTokenOut {},
 }
@@ -0,0 +1,103 @@
+use cosmwasm_std::{Coin, Event};
+use osmosis_router::WeightedRoute;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+
+use crate::{msg::AfterSwapAction, state::IbcTransferReplyState};
+
+// All events carry the correlation id of the swap, which is missing for transfers sent by
+// releases before the id was introduced.
+const CORRELATION_ID: &str = "correlation_id";
+
+/// Pool ids of the path separated by commas, split routes are separated by semicolons.
+pub fn pool_path(path: &[SwapAmountInRoute], split_routes: &[WeightedRoute]) -> String {
+    let format_path = |path: &[SwapAmountInRoute]| {
+        path.iter()
+            .map(|route| route.pool_id.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    match split_routes.is_empty() {
+        true => format_path(path),
+        false => split_routes
+            .iter()
+            .map(|route| format_path(&route.path))
+            .collect::<Vec<_>>()
+            .join(";"),
+    }
+}
+
+pub fn swap(correlation_id: u64, input: &Coin, output: &Coin, pool_path: &str) -> Event {
+    Event::new("squid_swap")
+        .add_attribute(CORRELATION_ID, correlation_id.to_string())
+        .add_attribute("input", input.to_string())
+        .add_attribute("output", output.to_string())
+        .add_attribute("pool_path", pool_path)
+}
+
+pub fn after_action(correlation_id: u64, action: &AfterSwapAction, coin: &Coin) -> Event {
+    let (name, receiver) = match action {
+        AfterSwapAction::BankSend { receiver } => ("bank_send", Some(receiver)),
+        AfterSwapAction::CustomCall {
+            contract_address, ..
+        } => ("custom_call", Some(contract_address)),
+        AfterSwapAction::IbcTransfer { receiver, .. } => ("ibc_transfer", Some(receiver)),
+        AfterSwapAction::RemoteSquidCall {
+            contract_address, ..
+        } => ("remote_squid_call", Some(contract_address)),
+        AfterSwapAction::AxelarGmp {
+            destination_address,
+            ..
+        } => ("axelar_gmp", Some(destination_address)),
+        AfterSwapAction::Split { .. } => ("split", None),
+    };
+
+    let event = Event::new("squid_after_action")
+        .add_attribute(CORRELATION_ID, correlation_id.to_string())
+        .add_attribute("action", name)
+        .add_attribute("coin", coin.to_string());
+
+    match receiver {
+        Some(receiver) => event.add_attribute("receiver", receiver),
+        None => event,
+    }
+}
+
+pub fn ibc_sent(transfer: &IbcTransferReplyState, sequence: u64) -> Event {
+    ibc_transfer_event("squid_ibc_sent", transfer, sequence)
+}
+
+/// `result` is one of `success`, `error` and `timeout`.
+pub fn ibc_ack(transfer: &IbcTransferReplyState, sequence: u64, result: &str) -> Event {
+    ibc_transfer_event("squid_ibc_ack", transfer, sequence).add_attribute("result", result)
+}
+
+/// The transfer was removed by the owner without a refund.
+pub fn ibc_dropped(transfer: &IbcTransferReplyState, sequence: u64) -> Event {
+    ibc_transfer_event("squid_ibc_dropped", transfer, sequence)
+}
+
+pub fn refund(correlation_id: Option<u64>, receiver: &str, coin: &Coin, reason: &str) -> Event {
+    with_correlation_id(Event::new("squid_refund"), correlation_id)
+        .add_attribute("receiver", receiver)
+        .add_attribute("coin", coin.to_string())
+        .add_attribute("reason", reason)
+}
+
+/// Carries the receiver and coin too, so acks of transfers without a correlation id can be
+/// joined on them.
+fn ibc_transfer_event(ty: &str, transfer: &IbcTransferReplyState, sequence: u64) -> Event {
+    with_correlation_id(Event::new(ty), transfer.correlation_id)
+        .add_attribute("channel", &transfer.channel)
+        .add_attribute("sequence", sequence.to_string())
+        .add_attribute("receiver", &transfer.receiver)
+        .add_attribute("amount", transfer.amount)
+        .add_attribute("denom", &transfer.denom)
+}
+
+fn with_correlation_id(event: Event, correlation_id: Option<u64>) -> Event {
+    match correlation_id {
+        Some(correlation_id) => event.add_attribute(CORRELATION_ID, correlation_id.to_string()),
+        None => event,
+    }
+}
@@ -1,6 +1,20 @@
-use cosmwasm_std::{BankMsg, Coin, DepsMut, Response};
+use cosmwasm_std::{BankMsg, Coin, Deps, DepsMut, Env, MessageInfo, Response, Timestamp};
 
-use crate::{state::load_awaiting_ibc_transfer_optional, ContractError};
+use crate::{
+    admin::ensure_owner,
+    commands::TRANSFER_PORT,
+    events,
+    msg::QueryUnreceivedAcksRequest,
+    state::{
+        load_awaiting_ibc_transfer_optional, load_awaiting_ibc_transfer_total, load_ownership,
+        load_unclaimed_fees, peek_awaiting_ibc_transfer_optional, IbcTransferReplyState,
+    },
+    ContractError,
+};
+
+/// Time after the packet timeout in which relayers are expected to deliver the timeout, before
+/// a transfer can be reclaimed.
+const RECLAIM_GRACE_PERIOD_SECONDS: u64 = 24 * 60 * 60;
 
 pub fn receive_ack(
     deps: DepsMut,
@@ -8,13 +22,18 @@ pub fn receive_ack(
     sequence: u64,
     success: bool,
 ) -> Result<Response, ContractError> {
-    if success {
-        // need to load awaiting transfer in order to remove it
-        let _ = load_awaiting_ibc_transfer_optional(deps.storage, &source_channel, sequence)?;
+    let Some(transfer) =
+        load_awaiting_ibc_transfer_optional(deps.storage, &source_channel, sequence)?
+    else {
         return Ok(Response::new());
+    };
+
+    if success {
+        return Ok(Response::new().add_event(events::ibc_ack(&transfer, sequence, "success")));
     }
 
-    send_funds_to_fallback_address(deps, source_channel, sequence)
+    let response = Response::new().add_event(events::ibc_ack(&transfer, sequence, "error"));
+    Ok(refund_transfer(response, transfer, "ibc_error"))
 }
 
 pub fn receive_timeout(
@@ -22,24 +41,458 @@ pub fn receive_timeout(
     source_channel: String,
     sequence: u64,
 ) -> Result<Response, ContractError> {
-    send_funds_to_fallback_address(deps, source_channel, sequence)
+    let Some(transfer) =
+        load_awaiting_ibc_transfer_optional(deps.storage, &source_channel, sequence)?
+    else {
+        return Ok(Response::new());
+    };
+
+    let response = Response::new().add_event(events::ibc_ack(&transfer, sequence, "timeout"));
+    Ok(refund_transfer(response, transfer, "ibc_timeout"))
 }
 
-fn send_funds_to_fallback_address(
+pub fn reclaim_ibc_transfer(
     deps: DepsMut,
-    source_channel: String,
+    env: &Env,
+    info: &MessageInfo,
+    channel: String,
     sequence: u64,
 ) -> Result<Response, ContractError> {
-    let Some(ibc_transfer_info) =
-        load_awaiting_ibc_transfer_optional(deps.storage, &source_channel, sequence)? else {
-            return Ok(Response::new());
+    let Some(transfer) = peek_awaiting_ibc_transfer_optional(deps.storage, &channel, sequence)?
+    else {
+        return Err(ContractError::AwaitingIbcTransferNotFound { channel, sequence });
+    };
+
+    let is_owner = load_ownership(deps.storage)?.owner == info.sender;
+    if !is_owner && info.sender != transfer.local_fallback_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match transfer.timeout_timestamp {
+        Some(timeout_timestamp) => {
+            let reclaimable_at =
+                Timestamp::from_nanos(timeout_timestamp).plus_seconds(RECLAIM_GRACE_PERIOD_SECONDS);
+            if env.block.time < reclaimable_at {
+                return Err(invalid_reclaim("timeout grace period has not passed"));
+            }
+        }
+        // the timeout height can't be checked on this chain
+        None if !is_owner => return Err(ContractError::Unauthorized {}),
+        None => {}
+    }
+
+    ensure_not_in_flight(deps.as_ref(), &channel, sequence)?;
+
+    // successfully acknowledged packets have no commitment either, but their funds never return,
+    // so a refund can only be told apart once it covers every awaiting transfer of the denom
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &transfer.denom)?
+        .amount;
+    let unclaimed_fees = load_unclaimed_fees(deps.storage, &transfer.denom)?;
+    let awaiting_total = load_awaiting_ibc_transfer_total(deps.storage, &transfer.denom)?;
+    if balance.saturating_sub(unclaimed_fees) < awaiting_total {
+        return Err(invalid_reclaim(
+            "refunded funds do not cover the awaiting transfers of the denom",
+        ));
+    }
+
+    // removes the transfer
+    let _ = load_awaiting_ibc_transfer_optional(deps.storage, &channel, sequence)?;
+
+    Ok(refund_transfer(Response::new(), transfer, "ibc_reclaimed")
+        .add_attribute("channel", channel)
+        .add_attribute("sequence", sequence.to_string()))
+}
+
+pub fn drop_ibc_transfer(
+    deps: DepsMut,
+    info: &MessageInfo,
+    channel: String,
+    sequence: u64,
+) -> Result<Response, ContractError> {
+    ensure_owner(deps.as_ref(), info)?;
+
+    let Some(transfer) = peek_awaiting_ibc_transfer_optional(deps.storage, &channel, sequence)?
+    else {
+        return Err(ContractError::AwaitingIbcTransferNotFound { channel, sequence });
+    };
+
+    // an ack or timeout of a packet in flight still refunds it
+    ensure_not_in_flight(deps.as_ref(), &channel, sequence)?;
+
+    // removes the transfer
+    let _ = load_awaiting_ibc_transfer_optional(deps.storage, &channel, sequence)?;
+
+    Ok(Response::new().add_event(events::ibc_dropped(&transfer, sequence)))
+}
+
+/// The commitment is removed once the packet is acknowledged or timed out.
+fn ensure_not_in_flight(deps: Deps, channel: &str, sequence: u64) -> Result<(), ContractError> {
+    let unreceived_acks = QueryUnreceivedAcksRequest {
+        port_id: TRANSFER_PORT.to_owned(),
+        channel_id: channel.to_owned(),
+        packet_ack_sequences: vec![sequence],
+    }
+    .query(&deps.querier)?;
+    if unreceived_acks.sequences.contains(&sequence) {
+        return Err(invalid_reclaim("packet is still in flight"));
+    }
+
+    Ok(())
+}
+
+/// Sends the funds of a failed, timed out or reclaimed transfer to its fallback address.
+fn refund_transfer(response: Response, transfer: IbcTransferReplyState, reason: &str) -> Response {
+    let coin = Coin {
+        denom: transfer.denom,
+        amount: transfer.amount,
+    };
+
+    response
+        .add_event(events::refund(
+            transfer.correlation_id,
+            &transfer.local_fallback_address,
+            &coin,
+            reason,
+        ))
+        .add_message(BankMsg::Send {
+            to_address: transfer.local_fallback_address,
+            amount: vec![coin],
+        })
+}
+
+fn invalid_reclaim(msg: &str) -> ContractError {
+    ContractError::InvalidIbcReclaim {
+        msg: msg.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use cosmwasm_std::{
+        from_slice,
+        testing::{mock_env, mock_info, MockApi, MockQuerier, MockStorage},
+        Addr, ContractResult, CosmosMsg, Empty, OwnedDeps, Querier, QuerierResult, QueryRequest,
+        SystemResult, Uint128,
+    };
+
+    use osmosis_router::OsmosisSwapMsg;
+
+    use super::*;
+    use crate::{
+        commands::handle_multiswap,
+        contract::sudo,
+        msg::{AfterSwapAction, IBCLifecycleComplete, MultiSwapMsg, SudoMsg},
+        state::{
+            add_accrued_fee, store_awaiting_ibc_transfer, store_ownership, store_pause_state,
+            Ownership, PauseState,
+        },
+    };
+
+    const CHANNEL: &str = "channel-0";
+    const DENOM: &str = "uosmo";
+
+    /// Bank querier that also answers `UnreceivedAcks`, packets in `in_flight` still have a
+    /// commitment.
+    struct ReclaimQuerier {
+        bank: MockQuerier,
+        in_flight: Vec<u64>,
+    }
+
+    impl Querier for ReclaimQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_slice(bin_request) {
+                Ok(QueryRequest::<Empty>::Stargate { path, data })
+                    if path == "/ibc.core.channel.v1.Query/UnreceivedAcks" =>
+                {
+                    let request = QueryUnreceivedAcksRequest::try_from(data).unwrap();
+                    let sequences: Vec<String> = request
+                        .packet_ack_sequences
+                        .into_iter()
+                        .filter(|sequence| self.in_flight.contains(sequence))
+                        .map(|sequence| format!("\"{sequence}\""))
+                        .collect();
+                    // proto-JSON, as the chain returns it
+                    let response = format!(
+                        r#"{{"sequences":[{}],"height":{{"revision_number":"1","revision_height":"100"}}}}"#,
+                        sequences.join(",")
+                    );
+                    SystemResult::Ok(ContractResult::Ok(response.into_bytes().into()))
+                }
+                _ => self.bank.raw_query(bin_request),
+            }
+        }
+    }
+
+    /// Contract holding `balance` with the transfers of `amounts` awaiting an ack since before
+    /// the grace period, with sequences starting at 1.
+    fn setup(
+        balance: u128,
+        amounts: &[u128],
+        in_flight: &[u64],
+    ) -> OwnedDeps<MockStorage, MockApi, ReclaimQuerier> {
+        let env = mock_env();
+        let mut deps = OwnedDeps {
+            storage: MockStorage::new(),
+            api: MockApi::default(),
+            querier: ReclaimQuerier {
+                bank: MockQuerier::new(&[(
+                    env.contract.address.as_str(),
+                    &[Coin::new(balance, DENOM)],
+                )]),
+                in_flight: in_flight.to_vec(),
+            },
+            custom_query_type: PhantomData,
         };
 
-    Ok(Response::new().add_message(BankMsg::Send {
-        to_address: ibc_transfer_info.local_fallback_address,
-        amount: vec![Coin {
-            denom: ibc_transfer_info.denom,
-            amount: ibc_transfer_info.amount,
-        }],
-    }))
+        store_ownership(
+            deps.as_mut().storage,
+            &Ownership {
+                owner: Addr::unchecked("owner"),
+                pending_owner: None,
+            },
+        )
+        .unwrap();
+
+        let timeout = env
+            .block
+            .time
+            .minus_seconds(2 * RECLAIM_GRACE_PERIOD_SECONDS);
+        for (sequence, amount) in (1..).zip(amounts) {
+            let transfer = IbcTransferReplyState {
+                local_fallback_address: "fallback".to_owned(),
+                channel: CHANNEL.to_owned(),
+                denom: DENOM.to_owned(),
+                amount: Uint128::new(*amount),
+                timeout_timestamp: Some(timeout.nanos()),
+                correlation_id: None,
+                receiver: "receiver".to_owned(),
+            };
+            store_awaiting_ibc_transfer(deps.as_mut().storage, sequence, &transfer).unwrap();
+        }
+
+        deps
+    }
+
+    fn reclaim(
+        deps: &mut OwnedDeps<MockStorage, MockApi, ReclaimQuerier>,
+        sequence: u64,
+    ) -> Result<Response, ContractError> {
+        reclaim_ibc_transfer(
+            deps.as_mut(),
+            &mock_env(),
+            &mock_info("fallback", &[]),
+            CHANNEL.to_owned(),
+            sequence,
+        )
+    }
+
+    fn assert_invalid_reclaim(result: Result<Response, ContractError>, expected: &str) {
+        match result {
+            Err(ContractError::InvalidIbcReclaim { msg }) => assert_eq!(msg, expected),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    fn assert_refunded(response: Response, amount: u128) {
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fallback".to_owned(),
+                amount: vec![Coin::new(amount, DENOM)],
+            })
+        );
+    }
+
+    #[test]
+    fn reclaims_refunded_transfer() {
+        let mut deps = setup(100, &[100], &[]);
+
+        assert_refunded(reclaim(&mut deps, 1).unwrap(), 100);
+        assert!(
+            peek_awaiting_ibc_transfer_optional(deps.as_ref().storage, CHANNEL, 1)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn refuses_reclaim_of_acknowledged_transfer() {
+        // the funds left with the packet, the balance only holds fees
+        let mut deps = setup(100, &[100], &[]);
+        add_accrued_fee(deps.as_mut().storage, &Coin::new(100, DENOM)).unwrap();
+
+        assert_invalid_reclaim(
+            reclaim(&mut deps, 1),
+            "refunded funds do not cover the awaiting transfers of the denom",
+        );
+    }
+
+    #[test]
+    fn refuses_reclaim_while_refund_could_belong_to_another_transfer() {
+        // one of the transfers was acknowledged and the other refunded, which can't be told apart
+        let mut deps = setup(100, &[100, 100], &[]);
+
+        for sequence in [1, 2] {
+            assert_invalid_reclaim(
+                reclaim(&mut deps, sequence),
+                "refunded funds do not cover the awaiting transfers of the denom",
+            );
+        }
+    }
+
+    #[test]
+    fn reclaims_when_refunds_cover_every_transfer_without_commitment() {
+        let mut deps = setup(200, &[100, 100], &[]);
+
+        assert_refunded(reclaim(&mut deps, 2).unwrap(), 100);
+    }
+
+    #[test]
+    fn waits_for_transfers_still_in_flight() {
+        let mut deps = setup(100, &[100, 100], &[1]);
+
+        assert_invalid_reclaim(reclaim(&mut deps, 1), "packet is still in flight");
+        // the funds of the first transfer could be in the balance as well
+        assert_invalid_reclaim(
+            reclaim(&mut deps, 2),
+            "refunded funds do not cover the awaiting transfers of the denom",
+        );
+
+        receive_timeout(deps.as_mut(), CHANNEL.to_owned(), 1).unwrap();
+        assert_refunded(reclaim(&mut deps, 2).unwrap(), 100);
+    }
+
+    #[test]
+    fn refuses_reclaim_after_balance_was_drained() {
+        // the refund of the transfer left the contract balance, fees can't pay for the reclaim
+        let mut deps = setup(150, &[100], &[]);
+        add_accrued_fee(deps.as_mut().storage, &Coin::new(100, DENOM)).unwrap();
+
+        assert_invalid_reclaim(
+            reclaim(&mut deps, 1),
+            "refunded funds do not cover the awaiting transfers of the denom",
+        );
+    }
+
+    #[test]
+    fn multiswap_without_funds_cannot_drain_refund() {
+        let mut deps = setup(100, &[100], &[]);
+
+        let err = handle_multiswap(
+            deps.as_mut(),
+            &mock_env(),
+            &mock_info("attacker", &[]),
+            vec![MultiSwapMsg {
+                amount_in: Coin::new(100, DENOM),
+                swap_msg: OsmosisSwapMsg {
+                    token_out_min_amount: Some("1".to_owned()),
+                    path: vec![],
+                    split_routes: vec![],
+                    twap_slippage: None,
+                },
+                after_swap_action: AfterSwapAction::BankSend {
+                    receiver: "attacker".to_owned(),
+                },
+            }],
+            "attacker".to_owned(),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidMultiSwapFunds {}));
+
+        assert_refunded(reclaim(&mut deps, 1).unwrap(), 100);
+    }
+
+    fn drop_transfer(
+        deps: &mut OwnedDeps<MockStorage, MockApi, ReclaimQuerier>,
+        sender: &str,
+        sequence: u64,
+    ) -> Result<Response, ContractError> {
+        drop_ibc_transfer(
+            deps.as_mut(),
+            &mock_info(sender, &[]),
+            CHANNEL.to_owned(),
+            sequence,
+        )
+    }
+
+    #[test]
+    fn dropping_acknowledged_transfer_unblocks_reclaims() {
+        // the first transfer was acknowledged but its ack never reached the contract
+        let mut deps = setup(100, &[100, 100], &[]);
+
+        let response = drop_transfer(&mut deps, "owner", 1).unwrap();
+        assert!(response.messages.is_empty());
+        assert_eq!(response.events[0].ty, "squid_ibc_dropped");
+
+        assert_refunded(reclaim(&mut deps, 2).unwrap(), 100);
+    }
+
+    #[test]
+    fn refuses_drop_by_non_owner_or_of_transfer_in_flight() {
+        let mut deps = setup(100, &[100], &[1]);
+
+        let err = drop_transfer(&mut deps, "fallback", 1).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        assert_invalid_reclaim(
+            drop_transfer(&mut deps, "owner", 1),
+            "packet is still in flight",
+        );
+    }
+
+    #[test]
+    fn ack_event_carries_receiver_and_coin() {
+        let mut deps = setup(0, &[100], &[]);
+
+        let response = receive_ack(deps.as_mut(), CHANNEL.to_owned(), 1, true).unwrap();
+
+        let event = &response.events[0];
+        assert_eq!(event.ty, "squid_ibc_ack");
+        for (key, value) in [
+            ("receiver", "receiver"),
+            ("amount", "100"),
+            ("denom", DENOM),
+            ("result", "success"),
+        ] {
+            assert!(event
+                .attributes
+                .iter()
+                .any(|attribute| attribute.key == key && attribute.value == value));
+        }
+    }
+
+    #[test]
+    fn refunds_failed_and_timed_out_transfers_while_paused() {
+        let mut deps = setup(200, &[100, 100], &[]);
+        let paused = PauseState {
+            swaps: true,
+            multi_swap: true,
+            ibc_transfers: true,
+        };
+        store_pause_state(deps.as_mut().storage, &paused).unwrap();
+
+        let ack = IBCLifecycleComplete::IBCAck {
+            channel: CHANNEL.to_owned(),
+            sequence: 1,
+            ack: String::new(),
+            success: false,
+        };
+        let timeout = IBCLifecycleComplete::IBCTimeout {
+            channel: CHANNEL.to_owned(),
+            sequence: 2,
+        };
+        for msg in [ack, timeout] {
+            let response = sudo(
+                deps.as_mut(),
+                mock_env(),
+                SudoMsg::IBCLifecycleComplete(msg),
+            );
+            assert_refunded(response.unwrap(), 100);
+        }
+    }
 }
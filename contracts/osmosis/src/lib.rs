@@ -1,8 +1,14 @@
+mod abi;
+mod admin;
 pub mod commands;
 pub mod contract;
 mod error;
+mod events;
 mod ibc;
+mod migrations;
 pub mod msg;
+mod proto_json;
+mod queries;
 pub mod state;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,187 @@
+use cosmwasm_std::{Api, Storage};
+use cw2::ContractVersion;
+use semver::Version;
+
+use crate::{
+    msg::MigrateMsg,
+    state::{
+        load_config_optional, load_ownership_optional, migrate_awaiting_ibc_transfers,
+        store_config, store_ownership, Config, IbcTransferReplyState, Ownership,
+    },
+    ContractError,
+};
+
+/// First release with the fallback address index and the totals by denom of in-flight transfers.
+const INDEX_VERSION: &str = "0.2.0";
+
+/// Refuses to migrate from another contract or from a newer version.
+pub fn ensure_upgrade(
+    stored: Option<&ContractVersion>,
+    contract_name: &str,
+    contract_version: &str,
+) -> Result<(), ContractError> {
+    // releases before version tracking can always be upgraded
+    let Some(stored) = stored else {
+        return Ok(());
+    };
+
+    if stored.contract != contract_name {
+        return Err(ContractError::InvalidMigration {
+            msg: format!("cannot migrate from contract {}", stored.contract),
+        });
+    }
+
+    if parse_version(&stored.version)? > parse_version(contract_version)? {
+        return Err(ContractError::InvalidMigration {
+            msg: format!(
+                "cannot downgrade from {} to {contract_version}",
+                stored.version
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Initializes the state introduced since the first release, which had neither a config nor an
+/// owner. Its in-flight transfers are rewritten by `index_awaiting_ibc_transfers`.
+pub fn migrate_from_unversioned(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    msg: MigrateMsg,
+    default_config: Config,
+) -> Result<(), ContractError> {
+    if load_ownership_optional(storage)?.is_none() {
+        let Some(owner) = msg.owner else {
+            return Err(ContractError::InvalidMigration {
+                msg: "owner is required when migrating from an unversioned release".to_owned(),
+            });
+        };
+
+        store_ownership(
+            storage,
+            &Ownership {
+                owner: api.addr_validate(&owner)?,
+                pending_owner: None,
+            },
+        )?;
+    }
+
+    if load_config_optional(storage)?.is_none() {
+        store_config(storage, &default_config)?;
+    }
+
+    Ok(())
+}
+
+/// Adds in-flight transfers to the fallback address index and the totals by denom when migrating
+/// from a release that predates them. Every in-flight transfer is rewritten, so later migrations
+/// skip this.
+pub fn index_awaiting_ibc_transfers(
+    storage: &mut dyn Storage,
+    stored: Option<&ContractVersion>,
+) -> Result<(), ContractError> {
+    if let Some(stored) = stored {
+        if parse_version(&stored.version)? >= parse_version(INDEX_VERSION)? {
+            return Ok(());
+        }
+    }
+
+    migrate_awaiting_ibc_transfers(storage, |data: IbcTransferReplyState| data)?;
+
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    Version::parse(version).map_err(|e| ContractError::InvalidMigration {
+        msg: format!("invalid version {version}: {e}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{testing::MockStorage, Uint128};
+    use cw_storage_plus::Map;
+
+    use super::*;
+    use crate::state::{
+        load_awaiting_ibc_transfer_total, load_awaiting_ibc_transfers_by_fallback_address,
+    };
+
+    fn store_unindexed_transfer(storage: &mut dyn Storage) {
+        let transfer = IbcTransferReplyState {
+            local_fallback_address: "fallback".to_owned(),
+            channel: "channel-0".to_owned(),
+            denom: "uosmo".to_owned(),
+            amount: Uint128::new(100),
+            timeout_timestamp: None,
+            correlation_id: None,
+            receiver: "receiver".to_owned(),
+        };
+        Map::new("awaiting_ibc_transfers")
+            .save(storage, ("channel-0", 1u64), &transfer)
+            .unwrap();
+    }
+
+    fn indexed_transfers(storage: &dyn Storage) -> (usize, u128) {
+        let by_fallback_address =
+            load_awaiting_ibc_transfers_by_fallback_address(storage, "fallback", None, 10).unwrap();
+        let total = load_awaiting_ibc_transfer_total(storage, "uosmo").unwrap();
+        (by_fallback_address.len(), total.u128())
+    }
+
+    fn version(version: &str) -> ContractVersion {
+        ContractVersion {
+            contract: "crates.io:osmosis".to_owned(),
+            version: version.to_owned(),
+        }
+    }
+
+    #[test]
+    fn indexes_transfers_from_releases_before_the_index() {
+        for stored in [None, Some(version("0.1.0"))] {
+            let mut storage = MockStorage::new();
+            store_unindexed_transfer(&mut storage);
+
+            index_awaiting_ibc_transfers(&mut storage, stored.as_ref()).unwrap();
+
+            assert_eq!(indexed_transfers(&storage), (1, 100));
+        }
+    }
+
+    #[test]
+    fn skips_indexing_from_releases_with_the_index() {
+        let mut storage = MockStorage::new();
+        store_unindexed_transfer(&mut storage);
+
+        index_awaiting_ibc_transfers(&mut storage, Some(&version("0.2.0"))).unwrap();
+
+        assert_eq!(indexed_transfers(&storage), (0, 0));
+    }
+
+    #[test]
+    fn upgrades_unversioned_and_older_releases() {
+        for stored in [None, Some(version("0.1.0")), Some(version("0.2.0"))] {
+            ensure_upgrade(stored.as_ref(), "crates.io:osmosis", "0.2.0").unwrap();
+        }
+    }
+
+    #[test]
+    fn refuses_downgrade() {
+        let err =
+            ensure_upgrade(Some(&version("0.3.0")), "crates.io:osmosis", "0.2.0").unwrap_err();
+
+        assert!(matches!(err, ContractError::InvalidMigration { .. }));
+    }
+
+    #[test]
+    fn refuses_migration_from_another_contract() {
+        let stored = ContractVersion {
+            contract: "crates.io:multicall".to_owned(),
+            version: "0.1.0".to_owned(),
+        };
+        let err = ensure_upgrade(Some(&stored), "crates.io:osmosis", "0.2.0").unwrap_err();
+
+        assert!(matches!(err, ContractError::InvalidMigration { .. }));
+    }
+}
@@ -1,25 +1,143 @@
 use enum_repr::EnumRepr;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Decimal};
-use osmosis_router::{OsmosisSimulateSwapResponse, OsmosisSwapMsg};
-use osmosis_std::types::osmosis::gamm::v1beta1::SwapAmountInRoute;
+use cosmwasm_std::{Binary, Coin, Decimal, Timestamp, Uint128};
+use multicall::BalanceInjection;
+use osmosis_router::{
+    OsmosisBestRouteResponse, OsmosisSimulateSwapResponse, OsmosisSwapExactAmountOutMsg,
+    OsmosisSwapMsg, TwapSlippage, TwapType,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
 use osmosis_std_derive::CosmwasmExt;
-use schemars::JsonSchema;
+
+use crate::state::{AxelarConfig, Config, Integrator, Ownership, PauseState};
+
+pub use multicall::SerializableJson;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Defaults to the instantiator.
+    pub owner: Option<String>,
+    pub ibc_packet_lifetime: Option<u64>,
+    pub guardian: Option<String>,
+    pub protocol_fee_bps: Option<u16>,
+    pub axelar: Option<AxelarConfig>,
+}
 
 #[cw_serde]
-pub struct InstantiateMsg {}
+pub struct MigrateMsg {
+    /// Required when migrating from a release without stored ownership.
+    pub owner: Option<String>,
+}
 
+// Fields added after the first release are left out of the serialized message while unset, so
+// remote Squid calls stay readable by earlier releases, which reject unknown fields.
 #[cw_serde]
 pub enum ExecuteMsg {
     SwapWithAction {
         swap_msg: OsmosisSwapMsg,
         after_swap_action: AfterSwapAction,
         local_fallback_address: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        integrator_id: Option<String>,
+        /// Sends the input to `local_fallback_address` instead of failing if the swap fails.
+        #[serde(default, skip_serializing_if = "is_false")]
+        refund_on_swap_failure: bool,
+    },
+    SwapExactAmountOutWithAction {
+        swap_msg: OsmosisSwapExactAmountOutMsg,
+        after_swap_action: AfterSwapAction,
+        local_fallback_address: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        refund_on_swap_failure: bool,
     },
     MultiSwap {
         swaps: Vec<MultiSwapMsg>,
         local_fallback_address: String,
+        integrator_id: Option<String>,
+    },
+    SwapWithRegisteredRoute {
+        output_denom: String,
+        token_out_min_amount: Option<String>,
+        twap_slippage: Option<TwapSlippage>,
+        after_swap_action: AfterSwapAction,
+        local_fallback_address: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        refund_on_swap_failure: bool,
+    },
+    /// Swap requested by an EVM contract through Axelar GMP, see `abi::decode_gmp_swap_request`
+    /// for the payload layout. The sender is not checked, so `source_chain` and `source_address`
+    /// are only reported as claimed by the caller.
+    AxelarGmpSwap {
+        source_chain: String,
+        source_address: String,
+        payload: Binary,
+    },
+    SetRoute {
+        input_denom: String,
+        output_denom: String,
+        path: Vec<SwapAmountInRoute>,
+    },
+    RemoveRoute {
+        input_denom: String,
+        output_denom: String,
+    },
+    UpdateConfig {
+        ibc_packet_lifetime: Option<u64>,
+        protocol_fee_bps: Option<u16>,
+        axelar: Option<AxelarConfig>,
+    },
+    TransferOwnership {
+        new_owner: String,
+    },
+    AcceptOwnership {},
+    /// Owner only, `None` removes the guardian.
+    SetGuardian {
+        guardian: Option<String>,
+    },
+    /// The guardian may only pause, the owner may also unpause.
+    SetPauseState {
+        swaps: Option<bool>,
+        multi_swap: Option<bool>,
+        ibc_transfers: Option<bool>,
+    },
+    /// Sends all accrued protocol fees to the recipient, defaults to the owner.
+    WithdrawFees {
+        recipient: Option<String>,
+    },
+    /// Owner only, overrides the packet lifetime for transfers over the channel. `None` resets
+    /// the channel to the configured default.
+    SetChannelPacketLifetime {
+        channel: String,
+        packet_lifetime: Option<u64>,
+    },
+    /// Owner only, adds or updates an integrator.
+    SetIntegrator {
+        id: String,
+        fee_bps: u16,
+        payout_address: String,
+    },
+    /// Owner only, pays out the accrued fees before removing the integrator.
+    RemoveIntegrator {
+        id: String,
+    },
+    /// Sends the accrued fees to the payout address, callable by the payout address.
+    ClaimIntegratorFees {
+        id: String,
+    },
+    /// Refunds an awaiting transfer whose ack or timeout was never delivered to the contract.
+    /// Callable by the fallback address or the owner once the packet timeout plus a grace
+    /// period has passed, transfers without a timeout timestamp by the owner only.
+    ReclaimIbcTransfer {
+        channel: String,
+        sequence: u64,
+    },
+    /// Owner only, removes an awaiting transfer whose packet commitment is gone without paying
+    /// it out. For transfers that were delivered but whose ack never reached the contract, which
+    /// would otherwise block reclaims of their denom.
+    DropIbcTransfer {
+        channel: String,
+        sequence: u64,
     },
 }
 
@@ -31,6 +149,55 @@ pub enum QueryMsg {
         input_coin: cosmwasm_std::Coin,
         path: Vec<SwapAmountInRoute>,
         slippage: Decimal,
+        twap_type: Option<TwapType>,
+        twap_window_seconds: Option<u64>,
+    },
+    #[returns(OsmosisBestRouteResponse)]
+    FindBestRoute {
+        input_coin: cosmwasm_std::Coin,
+        output_denom: String,
+        /// At most 8 candidate pools, pools without gamm liquidity are skipped.
+        pool_ids: Vec<u64>,
+        max_hops: u32,
+    },
+    #[returns(RouteResponse)]
+    Route {
+        input_denom: String,
+        output_denom: String,
+    },
+    #[returns(RoutesResponse)]
+    Routes {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(Config)]
+    Config {},
+    #[returns(Ownership)]
+    Ownership {},
+    #[returns(PauseState)]
+    PauseState {},
+    #[returns(AccruedFeesResponse)]
+    AccruedFees {},
+    #[returns(ChannelPacketLifetimeResponse)]
+    ChannelPacketLifetime { channel: String },
+    #[returns(Integrator)]
+    Integrator { id: String },
+    #[returns(AccruedFeesResponse)]
+    IntegratorFees { id: String },
+    #[returns(AwaitingIbcTransferResponse)]
+    AwaitingIbcTransfer { channel: String, sequence: u64 },
+    #[returns(AwaitingIbcTransfersResponse)]
+    AwaitingIbcTransfersByChannel {
+        channel: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Paginated by (channel, sequence).
+    #[returns(AwaitingIbcTransfersResponse)]
+    AwaitingIbcTransfersByFallbackAddress {
+        local_fallback_address: String,
+        start_after: Option<(String, u64)>,
+        limit: Option<u32>,
     },
     #[returns(PriceImpactTradeResponse)]
     EstimatePriceImpactTwapMinInputOutput{
@@ -56,14 +223,87 @@ pub enum AfterSwapAction {
     CustomCall {
         contract_address: String,
         msg: SerializableJson,
+        /// Injects the output amount sent with the call. Only the output denom can be injected.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        balance_injections: Vec<BalanceInjection>,
     },
     IbcTransfer {
         receiver: String,
         channel: String,
         next_memo: Option<SerializableJson>,
+        /// Absolute timeout, nanoseconds since unix epoch as a string.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_timestamp: Option<Timestamp>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_height: Option<IbcCounterpartyHeight>,
+        /// Further transfers done by the packet forward middleware starting at the receiving
+        /// chain. `next_memo` is passed along to the last hop.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        forward_hops: Vec<ForwardHop>,
+    },
+    /// Sends the output to a Squid contract on another chain over ibc-hooks and executes the
+    /// swap message there. The `wasm` memo is built by the contract.
+    RemoteSquidCall {
+        channel: String,
+        contract_address: String,
+        msg: Box<ExecuteMsg>,
+        /// Absolute timeout, nanoseconds since unix epoch as a string.
+        timeout_timestamp: Option<Timestamp>,
+        timeout_height: Option<IbcCounterpartyHeight>,
+    },
+    /// Sends the output to the Axelar gateway, which calls `destination_address` on the
+    /// destination chain with the payload.
+    AxelarGmp {
+        destination_chain: String,
+        destination_address: String,
+        /// ABI encoded payload.
+        payload: Binary,
+        message_type: GmpMessageType,
+        fee: Option<AxelarFee>,
+    },
+    /// Divides the swap output between several actions, which can not be splits themselves.
+    Split {
+        actions: Vec<SplitAction>,
     },
 }
 
+#[cw_serde]
+pub struct ForwardHop {
+    /// Channel on the chain forwarding the packet.
+    pub channel: String,
+    pub receiver: String,
+    pub timeout_seconds: Option<u64>,
+    pub retries: Option<u8>,
+}
+
+#[cw_serde]
+pub enum GmpMessageType {
+    /// The output only pays the fee, the payload is delivered without tokens.
+    Message,
+    MessageWithToken,
+}
+
+#[cw_serde]
+pub struct AxelarFee {
+    /// Paid out of the swap output.
+    pub amount: Uint128,
+    pub recipient: String,
+}
+
+#[cw_serde]
+pub struct SplitAction {
+    pub share: SplitShare,
+    pub action: AfterSwapAction,
+}
+
+#[cw_serde]
+pub enum SplitShare {
+    /// Share of the output left after all fixed amounts.
+    Weight(u64),
+    /// Fixed amount, paid before the weighted shares.
+    Amount(Uint128),
+}
+
 #[cw_serde]
 pub struct MultiSwapMsg {
     pub amount_in: Coin,
@@ -77,11 +317,56 @@ pub struct PriceImpactTradeResponse {
     pub amount_out: Coin,
 }
 
+#[cw_serde]
+pub struct ChannelPacketLifetimeResponse {
+    pub channel: String,
+    /// Packet lifetime in seconds applied when a transfer doesn't specify a timeout.
+    pub packet_lifetime: u64,
+}
+
+#[cw_serde]
+pub struct AccruedFeesResponse {
+    pub fees: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct RouteResponse {
+    pub input_denom: String,
+    pub output_denom: String,
+    pub path: Vec<SwapAmountInRoute>,
+}
+
+#[cw_serde]
+pub struct RoutesResponse {
+    pub routes: Vec<RouteResponse>,
+}
+
+#[cw_serde]
+pub struct AwaitingIbcTransferResponse {
+    pub channel: String,
+    pub sequence: u64,
+    pub local_fallback_address: String,
+    pub denom: String,
+    pub amount: Uint128,
+    pub timeout_timestamp: Option<Timestamp>,
+    /// Unset for transfers sent by earlier releases.
+    pub receiver: Option<String>,
+    /// Correlates the transfer with the events of its swap, unset for transfers sent by earlier
+    /// releases.
+    pub correlation_id: Option<u64>,
+}
+
+#[cw_serde]
+pub struct AwaitingIbcTransfersResponse {
+    pub transfers: Vec<AwaitingIbcTransferResponse>,
+}
+
 #[EnumRepr(type = "u64")]
 pub enum MsgReplyId {
     Swap = 1,
     IbcTransfer = 2,
     MultiSwap = 3,
+    CustomCall = 4,
 }
 
 #[cw_serde]
@@ -97,38 +382,6 @@ pub enum IBCLifecycleComplete {
     IBCTimeout { channel: String, sequence: u64 },
 }
 
-#[derive(
-    ::cosmwasm_schema::serde::Serialize,
-    ::cosmwasm_schema::serde::Deserialize,
-    ::std::clone::Clone,
-    ::std::fmt::Debug,
-    PartialEq,
-    Eq,
-)]
-pub struct SerializableJson(pub serde_cw_value::Value);
-
-impl JsonSchema for SerializableJson {
-    fn schema_name() -> String {
-        "JSON".to_string()
-    }
-
-    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
-        schemars::schema::Schema::from(true)
-    }
-}
-
-impl SerializableJson {
-    pub fn as_value(&self) -> &serde_cw_value::Value {
-        &self.0
-    }
-}
-
-impl From<serde_cw_value::Value> for SerializableJson {
-    fn from(value: serde_cw_value::Value) -> Self {
-        Self(value)
-    }
-}
-
 #[derive(
     Clone,
     PartialEq,
@@ -170,9 +423,17 @@ pub struct MsgTransfer {
 )]
 pub struct IbcCounterpartyHeight {
     #[prost(uint64, optional, tag = "1")]
-    revision_number: Option<u64>,
+    #[serde(
+        default,
+        deserialize_with = "crate::proto_json::as_str_option::deserialize"
+    )]
+    pub revision_number: Option<u64>,
     #[prost(uint64, optional, tag = "2")]
-    revision_height: Option<u64>,
+    #[serde(
+        default,
+        deserialize_with = "crate::proto_json::as_str_option::deserialize"
+    )]
+    pub revision_height: Option<u64>,
 }
 
 #[derive(Clone, PartialEq, Eq, ::prost::Message)]
@@ -180,3 +441,55 @@ pub struct MsgTransferResponse {
     #[prost(uint64, tag = "1")]
     pub sequence: u64,
 }
+
+/// Returns the sequences whose packet commitment still exists, i.e. that were neither
+/// acknowledged nor timed out on this chain yet.
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    ::prost::Message,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    CosmwasmExt,
+)]
+#[proto_message(type_url = "/ibc.core.channel.v1.QueryUnreceivedAcksRequest")]
+#[proto_query(
+    path = "/ibc.core.channel.v1.Query/UnreceivedAcks",
+    response_type = QueryUnreceivedAcksResponse
+)]
+pub struct QueryUnreceivedAcksRequest {
+    #[prost(string, tag = "1")]
+    pub port_id: String,
+    #[prost(string, tag = "2")]
+    pub channel_id: String,
+    #[prost(uint64, repeated, tag = "3")]
+    pub packet_ack_sequences: Vec<u64>,
+}
+
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    ::prost::Message,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    CosmwasmExt,
+)]
+#[proto_message(type_url = "/ibc.core.channel.v1.QueryUnreceivedAcksResponse")]
+pub struct QueryUnreceivedAcksResponse {
+    #[prost(uint64, repeated, tag = "1")]
+    #[serde(
+        serialize_with = "crate::proto_json::as_str_vec::serialize",
+        deserialize_with = "crate::proto_json::as_str_vec::deserialize"
+    )]
+    pub sequences: Vec<u64>,
+    #[prost(message, optional, tag = "2")]
+    pub height: Option<IbcCounterpartyHeight>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
@@ -0,0 +1,72 @@
+//! Serde helpers for stargate query responses, which reach the contract as proto-JSON where 64 bit
+//! integers are strings. Deserialization also accepts JSON numbers, so the same types can be used
+//! in contract messages.
+
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+
+/// `u64` read from either a string or a number.
+struct U64(u64);
+
+impl<'de> Deserialize<'de> for U64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(U64Visitor)
+    }
+}
+
+struct U64Visitor;
+
+impl<'de> Visitor<'de> for U64Visitor {
+    type Value = U64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an unsigned 64 bit integer or its string")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<U64, E> {
+        Ok(U64(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<U64, E> {
+        u64::try_from(value).map(U64).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<U64, E> {
+        value.parse().map(U64).map_err(E::custom)
+    }
+}
+
+pub mod as_str_vec {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::U64;
+
+    pub fn serialize<S: Serializer>(values: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(u64::to_string))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+        Ok(Vec::<U64>::deserialize(deserializer)?
+            .into_iter()
+            .map(|value| value.0)
+            .collect())
+    }
+}
+
+/// Only deserializes, `IbcCounterpartyHeight` is also part of the execute messages and keeps
+/// serializing numbers there.
+pub mod as_str_option {
+    use serde::{Deserialize, Deserializer};
+
+    use super::U64;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        Ok(Option::<U64>::deserialize(deserializer)?.map(|value| value.0))
+    }
+}
@@ -0,0 +1,197 @@
+use cosmwasm_std::{Deps, StdResult, Timestamp};
+
+use crate::{
+    msg::{
+        AccruedFeesResponse, AwaitingIbcTransferResponse, AwaitingIbcTransfersResponse,
+        ChannelPacketLifetimeResponse, RouteResponse, RoutesResponse,
+    },
+    state::{
+        load_accrued_fees, load_awaiting_ibc_transfers_by_channel,
+        load_awaiting_ibc_transfers_by_fallback_address, load_channel_packet_lifetime, load_config,
+        load_integrator_fees, load_integrator_optional, load_ownership, load_pause_state,
+        load_route_optional, load_routes, peek_awaiting_ibc_transfer_optional, Config,
+        IbcTransferReplyState, Integrator, Ownership, PauseState,
+    },
+    ContractError,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_config(deps: Deps) -> StdResult<Config> {
+    load_config(deps.storage)
+}
+
+pub fn query_ownership(deps: Deps) -> StdResult<Ownership> {
+    load_ownership(deps.storage)
+}
+
+pub fn query_pause_state(deps: Deps) -> StdResult<PauseState> {
+    load_pause_state(deps.storage)
+}
+
+pub fn query_accrued_fees(deps: Deps) -> StdResult<AccruedFeesResponse> {
+    Ok(AccruedFeesResponse {
+        fees: load_accrued_fees(deps.storage)?,
+    })
+}
+
+pub fn query_channel_packet_lifetime(
+    deps: Deps,
+    channel: String,
+) -> StdResult<ChannelPacketLifetimeResponse> {
+    let packet_lifetime = load_channel_packet_lifetime(deps.storage, &channel)?;
+
+    Ok(ChannelPacketLifetimeResponse {
+        channel,
+        packet_lifetime,
+    })
+}
+
+pub fn query_integrator(deps: Deps, id: String) -> Result<Integrator, ContractError> {
+    load_integrator_optional(deps.storage, &id)?.ok_or(ContractError::IntegratorNotFound { id })
+}
+
+pub fn query_integrator_fees(deps: Deps, id: String) -> StdResult<AccruedFeesResponse> {
+    Ok(AccruedFeesResponse {
+        fees: load_integrator_fees(deps.storage, &id)?,
+    })
+}
+
+pub fn query_route(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+) -> Result<RouteResponse, ContractError> {
+    let Some(path) = load_route_optional(deps.storage, &input_denom, &output_denom)? else {
+        return Err(ContractError::RouteNotFound {
+            input_denom,
+            output_denom,
+        });
+    };
+
+    Ok(RouteResponse {
+        input_denom,
+        output_denom,
+        path,
+    })
+}
+
+pub fn query_routes(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<RoutesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let routes = load_routes(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|((input_denom, output_denom), path)| RouteResponse {
+            input_denom,
+            output_denom,
+            path,
+        })
+        .collect();
+
+    Ok(RoutesResponse { routes })
+}
+
+pub fn query_awaiting_ibc_transfer(
+    deps: Deps,
+    channel: String,
+    sequence: u64,
+) -> Result<AwaitingIbcTransferResponse, ContractError> {
+    let Some(transfer) = peek_awaiting_ibc_transfer_optional(deps.storage, &channel, sequence)?
+    else {
+        return Err(ContractError::AwaitingIbcTransferNotFound { channel, sequence });
+    };
+
+    Ok(awaiting_ibc_transfer_response(sequence, transfer))
+}
+
+pub fn query_awaiting_ibc_transfers_by_channel(
+    deps: Deps,
+    channel: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<AwaitingIbcTransfersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let transfers =
+        load_awaiting_ibc_transfers_by_channel(deps.storage, &channel, start_after, limit)?
+            .into_iter()
+            .map(|(sequence, transfer)| awaiting_ibc_transfer_response(sequence, transfer))
+            .collect();
+
+    Ok(AwaitingIbcTransfersResponse { transfers })
+}
+
+pub fn query_awaiting_ibc_transfers_by_fallback_address(
+    deps: Deps,
+    local_fallback_address: String,
+    start_after: Option<(String, u64)>,
+    limit: Option<u32>,
+) -> StdResult<AwaitingIbcTransfersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let transfers = load_awaiting_ibc_transfers_by_fallback_address(
+        deps.storage,
+        &local_fallback_address,
+        start_after,
+        limit,
+    )?
+    .into_iter()
+    .map(|((_channel, sequence), transfer)| awaiting_ibc_transfer_response(sequence, transfer))
+    .collect();
+
+    Ok(AwaitingIbcTransfersResponse { transfers })
+}
+
+fn awaiting_ibc_transfer_response(
+    sequence: u64,
+    transfer: IbcTransferReplyState,
+) -> AwaitingIbcTransferResponse {
+    AwaitingIbcTransferResponse {
+        channel: transfer.channel,
+        sequence,
+        local_fallback_address: transfer.local_fallback_address,
+        denom: transfer.denom,
+        amount: transfer.amount,
+        timeout_timestamp: transfer.timeout_timestamp.map(Timestamp::from_nanos),
+        receiver: (!transfer.receiver.is_empty()).then_some(transfer.receiver),
+        correlation_id: transfer.correlation_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{testing::mock_dependencies, Uint128};
+
+    use super::*;
+    use crate::state::store_awaiting_ibc_transfer;
+
+    #[test]
+    fn awaiting_ibc_transfer_reports_receiver_and_correlation_id() {
+        let mut deps = mock_dependencies();
+        let transfer = IbcTransferReplyState {
+            local_fallback_address: "fallback".to_owned(),
+            channel: "channel-0".to_owned(),
+            denom: "uosmo".to_owned(),
+            amount: Uint128::new(100),
+            timeout_timestamp: Some(1_700_000_000_000_000_000),
+            correlation_id: Some(7),
+            receiver: "receiver".to_owned(),
+        };
+        store_awaiting_ibc_transfer(deps.as_mut().storage, 1, &transfer).unwrap();
+
+        let response =
+            query_awaiting_ibc_transfer(deps.as_ref(), "channel-0".to_owned(), 1).unwrap();
+
+        assert_eq!(response.receiver.as_deref(), Some("receiver"));
+        assert_eq!(response.correlation_id, Some(7));
+        assert_eq!(
+            response.timeout_timestamp,
+            Some(Timestamp::from_nanos(1_700_000_000_000_000_000))
+        );
+    }
+}
@@ -1,20 +1,129 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{StdResult, Storage, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Coin, Order, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Deque, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::msg::{AfterSwapAction, MultiSwapMsg};
 
+const CONFIG: Item<Config> = Item::new("config");
+const OWNERSHIP: Item<Ownership> = Item::new("ownership");
+const PAUSE_STATE: Item<PauseState> = Item::new("pause_state");
+const LAST_CORRELATION_ID: Item<u64> = Item::new("last_correlation_id");
+
 const SWAP_REPLY_STATE: Item<SwapReplyState> = Item::new("swap_reply_state");
-const IBC_TRANSFER_REPLY_STATE: Item<IbcTransferReplyState> = Item::new("ibc_transfer_reply_state");
-const AWAITING_IBC_TRANSFERS: Map<(&str, u64), IbcTransferReplyState> =
-    Map::new("awaiting_ibc_transfers");
+// Reply states are pushed to and popped from the front. Submessages run depth first, so this
+// keeps the states of nested swaps triggered by an after swap action apart from the outer ones,
+// as long as sibling submessages push their states in reverse order.
+const IBC_TRANSFER_REPLY_STATES: Deque<IbcTransferReplyState> =
+    Deque::new("ibc_transfer_reply_states");
+const CUSTOM_CALL_REPLY_STATES: Deque<CustomCallReplyState> =
+    Deque::new("custom_call_reply_states");
+const AWAITING_IBC_TRANSFERS_NAMESPACE: &str = "awaiting_ibc_transfers";
+const AWAITING_IBC_TRANSFERS_FALLBACK_NAMESPACE: &str = "awaiting_ibc_transfers__fallback";
+// Sum of the amounts of the in-flight transfers by denom.
+const AWAITING_IBC_TRANSFER_TOTALS: Map<&str, Uint128> = Map::new("awaiting_ibc_transfer_totals");
+
+pub type ChannelSequence = (String, u64);
+
+pub struct AwaitingIbcTransferIndexes<'a> {
+    pub fallback_address: MultiIndex<'a, String, IbcTransferReplyState, (&'a str, u64)>,
+}
+
+impl<'a> IndexList<IbcTransferReplyState> for AwaitingIbcTransferIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<IbcTransferReplyState>> + '_> {
+        let v: Vec<&dyn Index<IbcTransferReplyState>> = vec![&self.fallback_address];
+        Box::new(v.into_iter())
+    }
+}
+
+/// In-flight transfers by (channel, sequence), indexed by their fallback address.
+fn awaiting_ibc_transfers<'a>(
+) -> IndexedMap<'a, (&'a str, u64), IbcTransferReplyState, AwaitingIbcTransferIndexes<'a>> {
+    IndexedMap::new(
+        AWAITING_IBC_TRANSFERS_NAMESPACE,
+        AwaitingIbcTransferIndexes {
+            fallback_address: MultiIndex::new(
+                |_pk, data| data.local_fallback_address.clone(),
+                AWAITING_IBC_TRANSFERS_NAMESPACE,
+                AWAITING_IBC_TRANSFERS_FALLBACK_NAMESPACE,
+            ),
+        },
+    )
+}
 
 const MULTI_SWAP_STATE: Item<MultiSwapState> = Item::new("multi_swap_state");
 
+pub type DenomPair = (String, String);
+
+const ROUTES: Map<(&str, &str), Vec<SwapAmountInRoute>> = Map::new("routes");
+
+const CHANNEL_PACKET_LIFETIMES: Map<&str, u64> = Map::new("channel_packet_lifetimes");
+
+const ACCRUED_FEES: Map<&str, Uint128> = Map::new("accrued_fees");
+
+const INTEGRATORS: Map<&str, Integrator> = Map::new("integrators");
+const INTEGRATOR_FEES: Map<(&str, &str), Uint128> = Map::new("integrator_fees");
+
+#[cw_serde]
+pub struct Config {
+    /// Timeout of outgoing ibc transfers in seconds.
+    pub ibc_packet_lifetime: u64,
+    /// Address allowed to pause the contract next to the owner.
+    #[serde(default)]
+    pub guardian: Option<Addr>,
+    /// Protocol fee taken from the swap output in basis points.
+    #[serde(default)]
+    pub protocol_fee_bps: u16,
+    /// Required for Axelar GMP after swap actions.
+    #[serde(default)]
+    pub axelar: Option<AxelarConfig>,
+}
+
+#[cw_serde]
+pub struct AxelarConfig {
+    /// Channel from Osmosis to Axelar.
+    pub channel: String,
+    /// Axelar account receiving GMP transfers.
+    pub gateway_address: String,
+}
+
+#[cw_serde]
+pub struct Ownership {
+    pub owner: Addr,
+    pub pending_owner: Option<Addr>,
+}
+
+/// Circuit breaker flags. Refunds of failed or timed out ibc transfers are never paused.
+#[cw_serde]
+#[derive(Default)]
+pub struct PauseState {
+    /// Pauses single swaps, including the ones dispatched by a multi-swap.
+    pub swaps: bool,
+    pub multi_swap: bool,
+    /// Pauses the ibc transfer after swap action.
+    pub ibc_transfers: bool,
+}
+
+#[cw_serde]
+pub struct Integrator {
+    /// Fee taken from the swap output after the protocol fee in basis points.
+    pub fee_bps: u16,
+    pub payout_address: Addr,
+}
+
 #[cw_serde]
 pub struct SwapReplyState {
+    pub correlation_id: u64,
+    pub input_coin: Coin,
+    pub pool_path: String,
     pub after_swap_action: AfterSwapAction,
     pub local_fallback_address: String,
+    pub integrator_id: Option<String>,
 }
 
 #[cw_serde]
@@ -23,12 +132,89 @@ pub struct IbcTransferReplyState {
     pub channel: String,
     pub denom: String,
     pub amount: Uint128,
+    /// Absolute timeout in nanoseconds since unix epoch. Unset for transfers timing out by
+    /// height only and for transfers sent by earlier releases.
+    #[serde(default)]
+    pub timeout_timestamp: Option<u64>,
+    /// Unset for transfers sent by earlier releases.
+    #[serde(default)]
+    pub correlation_id: Option<u64>,
+    #[serde(default)]
+    pub receiver: String,
+}
+
+#[cw_serde]
+pub struct CustomCallReplyState {
+    pub correlation_id: u64,
+    pub local_fallback_address: String,
+    pub coin: Coin,
 }
 
 #[cw_serde]
 pub struct MultiSwapState {
     pub swaps: Vec<MultiSwapMsg>,
     pub local_fallback_address: String,
+    pub integrator_id: Option<String>,
+}
+
+/// Returns a new id correlating the events of a swap and its after swap action.
+pub fn next_correlation_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let correlation_id = LAST_CORRELATION_ID.may_load(storage)?.unwrap_or_default() + 1;
+    LAST_CORRELATION_ID.save(storage, &correlation_id)?;
+
+    Ok(correlation_id)
+}
+
+pub fn store_config(storage: &mut dyn Storage, data: &Config) -> StdResult<()> {
+    CONFIG.save(storage, data)
+}
+
+pub fn load_config(storage: &dyn Storage) -> StdResult<Config> {
+    CONFIG.load(storage)
+}
+
+pub fn load_config_optional(storage: &dyn Storage) -> StdResult<Option<Config>> {
+    CONFIG.may_load(storage)
+}
+
+pub fn store_ownership(storage: &mut dyn Storage, data: &Ownership) -> StdResult<()> {
+    OWNERSHIP.save(storage, data)
+}
+
+pub fn load_ownership(storage: &dyn Storage) -> StdResult<Ownership> {
+    OWNERSHIP.load(storage)
+}
+
+pub fn load_ownership_optional(storage: &dyn Storage) -> StdResult<Option<Ownership>> {
+    OWNERSHIP.may_load(storage)
+}
+
+pub fn store_channel_packet_lifetime(
+    storage: &mut dyn Storage,
+    channel: &str,
+    packet_lifetime: u64,
+) -> StdResult<()> {
+    CHANNEL_PACKET_LIFETIMES.save(storage, channel, &packet_lifetime)
+}
+
+pub fn remove_channel_packet_lifetime(storage: &mut dyn Storage, channel: &str) {
+    CHANNEL_PACKET_LIFETIMES.remove(storage, channel)
+}
+
+/// Packet lifetime of the channel, falls back to the configured default.
+pub fn load_channel_packet_lifetime(storage: &dyn Storage, channel: &str) -> StdResult<u64> {
+    match CHANNEL_PACKET_LIFETIMES.may_load(storage, channel)? {
+        Some(packet_lifetime) => Ok(packet_lifetime),
+        None => Ok(load_config(storage)?.ibc_packet_lifetime),
+    }
+}
+
+pub fn store_pause_state(storage: &mut dyn Storage, data: &PauseState) -> StdResult<()> {
+    PAUSE_STATE.save(storage, data)
+}
+
+pub fn load_pause_state(storage: &dyn Storage) -> StdResult<PauseState> {
+    Ok(PAUSE_STATE.may_load(storage)?.unwrap_or_default())
 }
 
 pub fn swap_reply_state_exists(storage: &dyn Storage) -> StdResult<bool> {
@@ -50,16 +236,28 @@ pub fn store_ibc_transfer_reply_state(
     storage: &mut dyn Storage,
     data: &IbcTransferReplyState,
 ) -> StdResult<()> {
-    IBC_TRANSFER_REPLY_STATE.save(storage, data)
+    IBC_TRANSFER_REPLY_STATES.push_front(storage, data)
 }
 
 pub fn load_ibc_transfer_reply_state(
     storage: &mut dyn Storage,
 ) -> StdResult<IbcTransferReplyState> {
-    let data = IBC_TRANSFER_REPLY_STATE.load(storage)?;
-    IBC_TRANSFER_REPLY_STATE.remove(storage);
+    IBC_TRANSFER_REPLY_STATES
+        .pop_front(storage)?
+        .ok_or_else(|| StdError::not_found("IbcTransferReplyState"))
+}
 
-    Ok(data)
+pub fn store_custom_call_reply_state(
+    storage: &mut dyn Storage,
+    data: &CustomCallReplyState,
+) -> StdResult<()> {
+    CUSTOM_CALL_REPLY_STATES.push_front(storage, data)
+}
+
+pub fn load_custom_call_reply_state(storage: &mut dyn Storage) -> StdResult<CustomCallReplyState> {
+    CUSTOM_CALL_REPLY_STATES
+        .pop_front(storage)?
+        .ok_or_else(|| StdError::not_found("CustomCallReplyState"))
 }
 
 pub fn store_awaiting_ibc_transfer(
@@ -67,7 +265,15 @@ pub fn store_awaiting_ibc_transfer(
     sequence: u64,
     data: &IbcTransferReplyState,
 ) -> StdResult<()> {
-    AWAITING_IBC_TRANSFERS.save(storage, (&data.channel, sequence), data)
+    awaiting_ibc_transfers().save(storage, (&data.channel, sequence), data)?;
+    AWAITING_IBC_TRANSFER_TOTALS.update(storage, &data.denom, |total| {
+        total
+            .unwrap_or_default()
+            .checked_add(data.amount)
+            .map_err(StdError::from)
+    })?;
+
+    Ok(())
 }
 
 pub fn load_awaiting_ibc_transfer_optional(
@@ -75,12 +281,105 @@ pub fn load_awaiting_ibc_transfer_optional(
     channel: &str,
     sequence: u64,
 ) -> StdResult<Option<IbcTransferReplyState>> {
-    let data = AWAITING_IBC_TRANSFERS.may_load(storage, (channel, sequence))?;
-    AWAITING_IBC_TRANSFERS.remove(storage, (channel, sequence));
+    let transfers = awaiting_ibc_transfers();
+    let data = transfers.may_load(storage, (channel, sequence))?;
+    transfers.remove(storage, (channel, sequence))?;
+
+    if let Some(data) = &data {
+        let total = load_awaiting_ibc_transfer_total(storage, &data.denom)?;
+        match total.saturating_sub(data.amount) {
+            total if total.is_zero() => AWAITING_IBC_TRANSFER_TOTALS.remove(storage, &data.denom),
+            total => AWAITING_IBC_TRANSFER_TOTALS.save(storage, &data.denom, &total)?,
+        }
+    }
 
     Ok(data)
 }
 
+/// Loads an in-flight transfer without removing it.
+pub fn peek_awaiting_ibc_transfer_optional(
+    storage: &dyn Storage,
+    channel: &str,
+    sequence: u64,
+) -> StdResult<Option<IbcTransferReplyState>> {
+    awaiting_ibc_transfers().may_load(storage, (channel, sequence))
+}
+
+pub fn load_awaiting_ibc_transfers_by_channel(
+    storage: &dyn Storage,
+    channel: &str,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<(u64, IbcTransferReplyState)>> {
+    awaiting_ibc_transfers()
+        .prefix(channel)
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect()
+}
+
+pub fn load_awaiting_ibc_transfers_by_fallback_address(
+    storage: &dyn Storage,
+    local_fallback_address: &str,
+    start_after: Option<ChannelSequence>,
+    limit: usize,
+) -> StdResult<Vec<(ChannelSequence, IbcTransferReplyState)>> {
+    let start = start_after
+        .as_ref()
+        .map(|(channel, sequence)| Bound::exclusive((channel.as_str(), *sequence)));
+
+    awaiting_ibc_transfers()
+        .idx
+        .fallback_address
+        .prefix(local_fallback_address.to_owned())
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+/// Sum of the amounts of all in-flight transfers of the denom.
+pub fn load_awaiting_ibc_transfer_total(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    Ok(AWAITING_IBC_TRANSFER_TOTALS
+        .may_load(storage, denom)?
+        .unwrap_or_default())
+}
+
+/// Rewrites in-flight transfers stored with a previous layout of `IbcTransferReplyState`, so
+/// their acks and timeouts can still be handled after a migration. This also adds them to the
+/// fallback address index and the totals by denom, which releases before 0.2.0 lack.
+pub fn migrate_awaiting_ibc_transfers<T: Serialize + DeserializeOwned>(
+    storage: &mut dyn Storage,
+    migrate: impl Fn(T) -> IbcTransferReplyState,
+) -> StdResult<()> {
+    let legacy: Map<(&str, u64), T> = Map::new(AWAITING_IBC_TRANSFERS_NAMESPACE);
+    let transfers = legacy
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // the previous value is not passed on, as it can't be read with the current layout
+    let awaiting_ibc_transfers = awaiting_ibc_transfers();
+    let mut totals: BTreeMap<String, Uint128> = BTreeMap::new();
+    for ((channel, sequence), data) in transfers {
+        let data = migrate(data);
+        awaiting_ibc_transfers.replace(storage, (&channel, sequence), Some(&data), None)?;
+
+        let total = totals.entry(data.denom).or_default();
+        *total = total.checked_add(data.amount)?;
+    }
+
+    // every transfer was rewritten, so the totals are complete
+    for (denom, total) in totals {
+        AWAITING_IBC_TRANSFER_TOTALS.save(storage, &denom, &total)?;
+    }
+
+    Ok(())
+}
+
 pub fn store_multi_swap_state(storage: &mut dyn Storage, data: &MultiSwapState) -> StdResult<()> {
     MULTI_SWAP_STATE.save(storage, data)
 }
@@ -92,3 +391,107 @@ pub fn load_multi_swap_state(storage: &dyn Storage) -> StdResult<MultiSwapState>
 pub fn remove_multi_swap_state(storage: &mut dyn Storage) {
     MULTI_SWAP_STATE.remove(storage);
 }
+
+pub fn store_route(
+    storage: &mut dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+    path: &Vec<SwapAmountInRoute>,
+) -> StdResult<()> {
+    ROUTES.save(storage, (input_denom, output_denom), path)
+}
+
+pub fn load_route_optional(
+    storage: &dyn Storage,
+    input_denom: &str,
+    output_denom: &str,
+) -> StdResult<Option<Vec<SwapAmountInRoute>>> {
+    ROUTES.may_load(storage, (input_denom, output_denom))
+}
+
+pub fn remove_route(storage: &mut dyn Storage, input_denom: &str, output_denom: &str) {
+    ROUTES.remove(storage, (input_denom, output_denom));
+}
+
+pub fn load_routes(
+    storage: &dyn Storage,
+    start_after: Option<(String, String)>,
+    limit: usize,
+) -> StdResult<Vec<(DenomPair, Vec<SwapAmountInRoute>)>> {
+    let start = start_after.as_ref().map(|(input_denom, output_denom)| {
+        Bound::exclusive((input_denom.as_str(), output_denom.as_str()))
+    });
+
+    ROUTES
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn add_accrued_fee(storage: &mut dyn Storage, fee: &Coin) -> StdResult<()> {
+    ACCRUED_FEES.update(storage, &fee.denom, |accrued| -> StdResult<_> {
+        Ok(accrued.unwrap_or_default().checked_add(fee.amount)?)
+    })?;
+
+    Ok(())
+}
+
+pub fn load_accrued_fees(storage: &dyn Storage) -> StdResult<Vec<Coin>> {
+    ACCRUED_FEES
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect()
+}
+
+pub fn clear_accrued_fees(storage: &mut dyn Storage) {
+    ACCRUED_FEES.clear(storage)
+}
+
+/// Protocol and integrator fees of the denom held by the contract.
+pub fn load_unclaimed_fees(storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+    let mut fees = ACCRUED_FEES.may_load(storage, denom)?.unwrap_or_default();
+    for item in INTEGRATOR_FEES.range(storage, None, None, Order::Ascending) {
+        let ((_id, fee_denom), amount) = item?;
+        if fee_denom == denom {
+            fees = fees.checked_add(amount)?;
+        }
+    }
+
+    Ok(fees)
+}
+
+pub fn store_integrator(storage: &mut dyn Storage, id: &str, data: &Integrator) -> StdResult<()> {
+    INTEGRATORS.save(storage, id, data)
+}
+
+pub fn load_integrator_optional(storage: &dyn Storage, id: &str) -> StdResult<Option<Integrator>> {
+    INTEGRATORS.may_load(storage, id)
+}
+
+pub fn remove_integrator(storage: &mut dyn Storage, id: &str) {
+    INTEGRATORS.remove(storage, id)
+}
+
+pub fn add_integrator_fee(storage: &mut dyn Storage, id: &str, fee: &Coin) -> StdResult<()> {
+    INTEGRATOR_FEES.update(storage, (id, &fee.denom), |accrued| -> StdResult<_> {
+        Ok(accrued.unwrap_or_default().checked_add(fee.amount)?)
+    })?;
+
+    Ok(())
+}
+
+pub fn load_integrator_fees(storage: &dyn Storage, id: &str) -> StdResult<Vec<Coin>> {
+    INTEGRATOR_FEES
+        .prefix(id)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect()
+}
+
+pub fn clear_integrator_fees(storage: &mut dyn Storage, id: &str) -> StdResult<()> {
+    for fee in load_integrator_fees(storage, id)? {
+        INTEGRATOR_FEES.remove(storage, (id, &fee.denom));
+    }
+
+    Ok(())
+}
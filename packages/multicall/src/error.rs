@@ -0,0 +1,20 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MulticallError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Multicall is already in process")]
+    MulticallIsAlreadyInProcess {},
+
+    #[error("Invalid amount of calls. Must be non-zero")]
+    InvalidAmountOfCalls {},
+
+    #[error("Invalid balance injection path: {path}")]
+    InvalidInjectionPath { path: String },
+
+    #[error("Invalid balance injection denom: {denom}")]
+    InvalidInjectionDenom { denom: String },
+}
@@ -0,0 +1,127 @@
+use cosmwasm_std::{CosmosMsg, DepsMut, Env, Storage};
+
+use crate::{
+    error::MulticallError,
+    injection::inject_balances,
+    state::{has_pending_calls, load_pending_calls, remove_pending_calls, store_pending_calls},
+    Call,
+};
+
+/// Stores the ordered list of calls to be executed one by one with
+/// [`build_next_call_msg`]. Calls are kept in reverse order so the next
+/// one can be popped from the end of the list.
+pub fn store_calls(storage: &mut dyn Storage, mut calls: Vec<Call>) -> Result<(), MulticallError> {
+    if has_pending_calls(storage)? {
+        return Err(MulticallError::MulticallIsAlreadyInProcess {});
+    }
+
+    if calls.is_empty() {
+        return Err(MulticallError::InvalidAmountOfCalls {});
+    }
+
+    calls.reverse();
+    store_pending_calls(storage, &calls)?;
+
+    Ok(())
+}
+
+/// Pops the next pending call and builds its message, injecting current
+/// balances where requested. Returns `None` and clears the state once every
+/// call has been dispatched.
+pub fn build_next_call_msg(deps: DepsMut, env: &Env) -> Result<Option<CosmosMsg>, MulticallError> {
+    let mut calls = load_pending_calls(deps.storage)?;
+    let Some(mut next_call) = calls.pop() else {
+        // all calls are done, remove state
+        remove_pending_calls(deps.storage);
+        return Ok(None);
+    };
+
+    if let Call::Wasm {
+        msg,
+        balance_injections,
+        ..
+    } = &mut next_call
+    {
+        inject_balances(
+            &deps.querier,
+            &env.contract.address,
+            msg,
+            balance_injections,
+        )?;
+    }
+
+    store_pending_calls(deps.storage, &calls)?;
+    Ok(Some(next_call.into_cosmos_msg()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_dependencies_with_balance, mock_env},
+        Binary, Coin, WasmMsg,
+    };
+
+    use super::*;
+    use crate::{error::MulticallError, BalanceInjection};
+
+    #[test]
+    fn rejects_empty_calls() {
+        let mut deps = mock_dependencies();
+
+        let err = store_calls(deps.as_mut().storage, vec![]).unwrap_err();
+
+        assert!(matches!(err, MulticallError::InvalidAmountOfCalls {}));
+    }
+
+    #[test]
+    fn rejects_calls_while_another_multicall_is_in_process() {
+        let mut deps = mock_dependencies();
+        let call = Call::Stargate {
+            type_url: "/cosmos.bank.v1beta1.MsgSend".to_owned(),
+            value: Default::default(),
+        };
+        store_calls(deps.as_mut().storage, vec![call.clone()]).unwrap();
+
+        let err = store_calls(deps.as_mut().storage, vec![call.clone()]).unwrap_err();
+        assert!(matches!(
+            err,
+            MulticallError::MulticallIsAlreadyInProcess {}
+        ));
+
+        // the last call was dispatched, the next multicall can start
+        build_next_call_msg(deps.as_mut(), &mock_env()).unwrap();
+        assert!(build_next_call_msg(deps.as_mut(), &mock_env())
+            .unwrap()
+            .is_none());
+        store_calls(deps.as_mut().storage, vec![call]).unwrap();
+    }
+
+    #[test]
+    fn injects_balance_right_before_the_call() {
+        let mut deps = mock_dependencies_with_balance(&[Coin::new(123, "uosmo")]);
+        let call = Call::Wasm {
+            contract_addr: "contract".to_owned(),
+            msg: serde_json_wasm::from_str(r#"{"swap":{"amount":"0"}}"#).unwrap(),
+            funds: vec![],
+            balance_injections: vec![BalanceInjection {
+                denom: "uosmo".to_owned(),
+                path: "/swap/amount".to_owned(),
+            }],
+        };
+        store_calls(deps.as_mut().storage, vec![call]).unwrap();
+
+        let msg = build_next_call_msg(deps.as_mut(), &mock_env()).unwrap();
+
+        assert_eq!(
+            msg,
+            Some(
+                WasmMsg::Execute {
+                    contract_addr: "contract".to_owned(),
+                    msg: Binary::from(br#"{"swap":{"amount":"123"}}"#),
+                    funds: vec![],
+                }
+                .into()
+            )
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use cosmwasm_std::{Addr, Coin, QuerierWrapper};
+use serde_cw_value::Value;
+
+use crate::{error::MulticallError, BalanceInjection, SerializableJson};
+
+/// Writes the current contract balance of each requested denom into `msg`
+/// at the given JSON pointer (e.g. `/swap/amount`). Every segment of the
+/// pointer but the last one must already exist in the message.
+pub fn inject_balances(
+    querier: &QuerierWrapper,
+    contract_address: &Addr,
+    msg: &mut SerializableJson,
+    injections: &[BalanceInjection],
+) -> Result<(), MulticallError> {
+    for injection in injections.iter() {
+        let balance = querier.query_balance(contract_address, &injection.denom)?;
+        set_value_at_path(
+            &mut msg.0,
+            &injection.path,
+            Value::String(balance.amount.to_string()),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes `coin.amount` into `msg` instead of the contract balance, for callers whose balance
+/// holds funds that don't belong to the call. Injections of other denoms are rejected.
+pub fn inject_amount(
+    msg: &mut SerializableJson,
+    injections: &[BalanceInjection],
+    coin: &Coin,
+) -> Result<(), MulticallError> {
+    for injection in injections.iter() {
+        if injection.denom != coin.denom {
+            return Err(MulticallError::InvalidInjectionDenom {
+                denom: injection.denom.clone(),
+            });
+        }
+
+        set_value_at_path(
+            &mut msg.0,
+            &injection.path,
+            Value::String(coin.amount.to_string()),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn set_value_at_path(root: &mut Value, path: &str, new_value: Value) -> Result<(), MulticallError> {
+    let invalid_path = || MulticallError::InvalidInjectionPath {
+        path: path.to_owned(),
+    };
+
+    let Some(pointer) = path.strip_prefix('/') else {
+        return Err(invalid_path());
+    };
+
+    let mut segments: Vec<String> = pointer
+        .split('/')
+        .map(|s| s.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    let last_segment = segments.pop().ok_or_else(invalid_path)?;
+
+    let mut target = root;
+    for segment in segments.iter() {
+        target = match target {
+            Value::Map(m) => m.get_mut(&Value::String(segment.to_owned())),
+            Value::Seq(s) => match segment.parse::<usize>() {
+                Ok(index) => s.get_mut(index),
+                Err(_) => None,
+            },
+            _ => None,
+        }
+        .ok_or_else(invalid_path)?;
+    }
+
+    match target {
+        Value::Map(m) => {
+            m.insert(Value::String(last_segment), new_value);
+        }
+        Value::Seq(s) => {
+            let index = last_segment.parse::<usize>().map_err(|_| invalid_path())?;
+            let item = s.get_mut(index).ok_or_else(invalid_path)?;
+            *item = new_value;
+        }
+        _ => return Err(invalid_path()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(value: &str) -> Value {
+        serde_json_wasm::from_str(value).unwrap()
+    }
+
+    fn set(root: &str, path: &str) -> Result<Value, MulticallError> {
+        let mut root = json(root);
+        set_value_at_path(&mut root, path, Value::String("100".to_owned()))?;
+        Ok(root)
+    }
+
+    fn assert_invalid_path(result: Result<Value, MulticallError>) {
+        assert!(matches!(
+            result,
+            Err(MulticallError::InvalidInjectionPath { .. })
+        ));
+    }
+
+    #[test]
+    fn sets_value_in_nested_map() {
+        let root = set(r#"{"swap":{"amount":"0","denom":"uosmo"}}"#, "/swap/amount").unwrap();
+
+        assert_eq!(root, json(r#"{"swap":{"amount":"100","denom":"uosmo"}}"#));
+    }
+
+    #[test]
+    fn sets_value_at_array_index() {
+        let root = set(r#"{"amounts":["0","0"]}"#, "/amounts/1").unwrap();
+
+        assert_eq!(root, json(r#"{"amounts":["0","100"]}"#));
+    }
+
+    #[test]
+    fn unescapes_segments() {
+        let root = set(r#"{"a/b":{"c~d":"0"}}"#, "/a~1b/c~0d").unwrap();
+
+        assert_eq!(root, json(r#"{"a/b":{"c~d":"100"}}"#));
+    }
+
+    #[test]
+    fn rejects_missing_parent() {
+        assert_invalid_path(set(r#"{"swap":{}}"#, "/transfer/amount"));
+    }
+
+    #[test]
+    fn rejects_path_without_leading_slash() {
+        assert_invalid_path(set(r#"{"amount":"0"}"#, "amount"));
+    }
+
+    #[test]
+    fn rejects_out_of_range_index() {
+        assert_invalid_path(set(r#"{"amounts":["0"]}"#, "/amounts/1"));
+    }
+}
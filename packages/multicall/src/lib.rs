@@ -1 +1,90 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_binary, BankMsg, Binary, Coin, CosmosMsg, WasmMsg};
+use error::MulticallError;
+use schemars::JsonSchema;
 
+pub mod error;
+pub mod executor;
+pub mod injection;
+pub mod state;
+
+#[cw_serde]
+pub enum Call {
+    Wasm {
+        contract_addr: String,
+        msg: SerializableJson,
+        funds: Vec<Coin>,
+        #[serde(default)]
+        balance_injections: Vec<BalanceInjection>,
+    },
+    Bank {
+        to_address: String,
+        amount: Vec<Coin>,
+    },
+    Stargate {
+        type_url: String,
+        value: Binary,
+    },
+}
+
+impl Call {
+    pub fn into_cosmos_msg(self) -> Result<CosmosMsg, MulticallError> {
+        let msg = match self {
+            Call::Wasm {
+                contract_addr,
+                msg,
+                funds,
+                ..
+            } => WasmMsg::Execute {
+                contract_addr,
+                msg: to_binary(&msg)?,
+                funds,
+            }
+            .into(),
+            Call::Bank { to_address, amount } => BankMsg::Send { to_address, amount }.into(),
+            Call::Stargate { type_url, value } => CosmosMsg::Stargate { type_url, value },
+        };
+
+        Ok(msg)
+    }
+}
+
+/// Replaces the value at JSON pointer `path` of a call message with the
+/// contract's balance of `denom` right before the call is executed.
+#[cw_serde]
+pub struct BalanceInjection {
+    pub denom: String,
+    pub path: String,
+}
+
+#[derive(
+    ::cosmwasm_schema::serde::Serialize,
+    ::cosmwasm_schema::serde::Deserialize,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    PartialEq,
+    Eq,
+)]
+pub struct SerializableJson(pub serde_cw_value::Value);
+
+impl JsonSchema for SerializableJson {
+    fn schema_name() -> String {
+        "JSON".to_string()
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::Schema::from(true)
+    }
+}
+
+impl SerializableJson {
+    pub fn as_value(&self) -> &serde_cw_value::Value {
+        &self.0
+    }
+}
+
+impl From<serde_cw_value::Value> for SerializableJson {
+    fn from(value: serde_cw_value::Value) -> Self {
+        Self(value)
+    }
+}
@@ -0,0 +1,22 @@
+use cosmwasm_std::{StdResult, Storage};
+use cw_storage_plus::Item;
+
+use crate::Call;
+
+const PENDING_CALLS: Item<Vec<Call>> = Item::new("multicall_pending_calls");
+
+pub(crate) fn has_pending_calls(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(PENDING_CALLS.may_load(storage)?.is_some())
+}
+
+pub(crate) fn store_pending_calls(storage: &mut dyn Storage, data: &Vec<Call>) -> StdResult<()> {
+    PENDING_CALLS.save(storage, data)
+}
+
+pub(crate) fn load_pending_calls(storage: &dyn Storage) -> StdResult<Vec<Call>> {
+    PENDING_CALLS.load(storage)
+}
+
+pub(crate) fn remove_pending_calls(storage: &mut dyn Storage) {
+    PENDING_CALLS.remove(storage);
+}
@@ -21,6 +21,12 @@ pub enum OsmosisRouterError {
     #[error("Invalid swap path")]
     InvalidPath {},
 
+    #[error("Path output denom {actual} does not match {expected}")]
+    OutputDenomMismatch { expected: String, actual: String },
+
+    #[error("Invalid split routes")]
+    InvalidSplitRoutes {},
+
     #[error("Input denom {denom} not found for pool {pool_id}")]
     InputDenomNotFound { denom: String, pool_id: String },
 
@@ -30,6 +36,27 @@ pub enum OsmosisRouterError {
     #[error("Invalid twap price")]
     InvalidTwapPrice {},
 
+    #[error("Invalid twap window")]
+    InvalidTwapWindow {},
+
+    #[error("Invalid slippage, must be a percentage between 0 and 100")]
+    InvalidSlippage {},
+
+    #[error("Exactly one of token_out_min_amount or twap_slippage must be set")]
+    InvalidMinOutput {},
+
+    #[error("Token in max amount {max_amount} exceeds provided amount {amount}")]
+    InvalidTokenInMaxAmount { max_amount: String, amount: String },
+
+    #[error("Invalid max hops, must be between 1 and {max}")]
+    InvalidMaxHops { max: u32 },
+
+    #[error("Too many pools, at most {max} are allowed")]
+    TooManyPools { max: usize },
+
+    #[error("No route found")]
+    RouteNotFound {},
+
     #[error("Swap failed. Reason: {reason}")]
     FailedSwap { reason: String },
 }
@@ -4,21 +4,34 @@ use std::ops::{Div, Mul};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Decimal, QuerierWrapper, Timestamp, Uint128};
 use error::OsmosisRouterError;
-use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{SwapAmountInRoute, SwapAmountOutRoute};
 use osmosis_std::{
     shim::Timestamp as OsmosisTimestamp, types::osmosis::twap::v1beta1::TwapQuerier,
 };
 
 pub mod error;
+pub mod poolmanager;
 pub mod router;
 pub mod state;
 
-const TWAP_WINDOW: u64 = 3600;
+pub const DEFAULT_TWAP_WINDOW: u64 = 3600;
 
 pub struct OsmosisPath(Vec<SwapAmountInRoute>);
 
 impl OsmosisPath {
-    pub fn validate_path(&self, input_denom: &str) -> Result<(), OsmosisRouterError> {
+    pub fn new(path: Vec<SwapAmountInRoute>) -> Self {
+        Self(path)
+    }
+
+    pub fn validate_path(
+        &self,
+        input_denom: &str,
+        output_denom: Option<&str>,
+    ) -> Result<(), OsmosisRouterError> {
+        if self.0.is_empty() {
+            return Err(OsmosisRouterError::InvalidPath {});
+        }
+
         let mut next_input_denom = input_denom;
         let mut seen_denoms: HashSet<&str> = [next_input_denom].iter().cloned().collect();
 
@@ -31,6 +44,15 @@ impl OsmosisPath {
             seen_denoms.insert(next_input_denom);
         }
 
+        if let Some(output_denom) = output_denom {
+            if next_input_denom != output_denom {
+                return Err(OsmosisRouterError::OutputDenomMismatch {
+                    expected: output_denom.to_owned(),
+                    actual: next_input_denom.to_owned(),
+                });
+            }
+        }
+
         Ok(())
     }
 
@@ -40,16 +62,23 @@ impl OsmosisPath {
         input_coin: &cosmwasm_std::Coin,
         slippage: Decimal,
         now: &Timestamp,
-    ) -> Result<cosmwasm_std::Coin, OsmosisRouterError> {
-        let (start_time, end_time) = self.get_twap_window(&now);
+        twap: &TwapParams,
+    ) -> Result<OsmosisSimulateSwapResponse, OsmosisRouterError> {
+        if slippage > Decimal::percent(100 * 100) {
+            return Err(OsmosisRouterError::InvalidSlippage {});
+        }
+
+        let (start_time, end_time) = self.get_twap_window(now, twap.window_seconds)?;
 
         let mut price = Decimal::one();
         let mut next_input_denom = input_coin.denom.as_str();
+        let mut hops = Vec::with_capacity(self.0.len());
 
         for step in self.0.iter() {
             let output_denom = step.token_out_denom.as_str();
 
-            let pool_price = self.get_arithmetic_twap_price(
+            let pool_price = self.get_twap_price(
+                &twap.twap_type,
                 step.pool_id,
                 querier,
                 next_input_denom,
@@ -59,14 +88,30 @@ impl OsmosisPath {
             )?;
 
             price = price.checked_mul(pool_price)?;
+            hops.push(TwapSwapHop {
+                pool_id: step.pool_id,
+                input_denom: next_input_denom.to_owned(),
+                output_denom: output_denom.to_owned(),
+                twap_price: pool_price,
+                amount_out: input_coin.amount.mul(price),
+            });
             next_input_denom = output_denom;
         }
 
         let price_with_slippage = price - price.mul(slippage.div(Uint128::new(100)));
+        let output_amount = input_coin.amount.mul(price_with_slippage);
+        let slippage_amount = input_coin.amount.mul(price).checked_sub(output_amount)?;
 
-        Ok(cosmwasm_std::Coin {
-            denom: next_input_denom.to_owned(),
-            amount: input_coin.amount.mul(price_with_slippage),
+        Ok(OsmosisSimulateSwapResponse {
+            output_coin: cosmwasm_std::Coin {
+                denom: next_input_denom.to_owned(),
+                amount: output_amount,
+            },
+            hops,
+            slippage_deduction: cosmwasm_std::Coin {
+                denom: next_input_denom.to_owned(),
+                amount: slippage_amount,
+            },
         })
     }
 
@@ -74,8 +119,26 @@ impl OsmosisPath {
         self.0.last().cloned().unwrap().token_out_denom
     }
 
-    fn get_arithmetic_twap_price(
+    pub fn to_amount_out_routes(&self, input_denom: &str) -> Vec<SwapAmountOutRoute> {
+        let mut next_input_denom = input_denom;
+
+        self.0
+            .iter()
+            .map(|step| {
+                let route = SwapAmountOutRoute {
+                    pool_id: step.pool_id,
+                    token_in_denom: next_input_denom.to_owned(),
+                };
+                next_input_denom = &step.token_out_denom;
+                route
+            })
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_twap_price(
         &self,
+        twap_type: &TwapType,
         pool_id: u64,
         querier: &QuerierWrapper,
         input_denom: &str,
@@ -83,16 +146,28 @@ impl OsmosisPath {
         start_time: OsmosisTimestamp,
         end_time: OsmosisTimestamp,
     ) -> Result<Decimal, OsmosisRouterError> {
-        let twap_price = TwapQuerier::new(querier)
-            .arithmetic_twap(
-                pool_id,
-                input_denom.to_owned(),
-                output_denom.to_owned(),
-                Some(start_time),
-                Some(end_time),
-            )
-            .map_err(|_| OsmosisRouterError::TwapPriceNotFound {})?
-            .arithmetic_twap;
+        let twap_querier = TwapQuerier::new(querier);
+        let twap_price = match twap_type {
+            TwapType::Arithmetic => twap_querier
+                .arithmetic_twap(
+                    pool_id,
+                    input_denom.to_owned(),
+                    output_denom.to_owned(),
+                    Some(start_time),
+                    Some(end_time),
+                )
+                .map(|res| res.arithmetic_twap),
+            TwapType::Geometric => twap_querier
+                .geometric_twap(
+                    pool_id,
+                    input_denom.to_owned(),
+                    output_denom.to_owned(),
+                    Some(start_time),
+                    Some(end_time),
+                )
+                .map(|res| res.geometric_twap),
+        }
+        .map_err(|_| OsmosisRouterError::TwapPriceNotFound {})?;
 
         let twap_price: Decimal = twap_price
             .parse()
@@ -101,8 +176,16 @@ impl OsmosisPath {
         Ok(twap_price)
     }
 
-    fn get_twap_window(&self, now: &Timestamp) -> (OsmosisTimestamp, OsmosisTimestamp) {
-        let start_time = now.minus_seconds(TWAP_WINDOW);
+    fn get_twap_window(
+        &self,
+        now: &Timestamp,
+        window_seconds: u64,
+    ) -> Result<(OsmosisTimestamp, OsmosisTimestamp), OsmosisRouterError> {
+        if window_seconds == 0 || window_seconds > now.seconds() {
+            return Err(OsmosisRouterError::InvalidTwapWindow {});
+        }
+
+        let start_time = now.minus_seconds(window_seconds);
         let start_time = OsmosisTimestamp {
             seconds: start_time.seconds() as i64,
             nanos: 0_i32,
@@ -113,22 +196,93 @@ impl OsmosisPath {
             nanos: 0_i32,
         };
 
-        (start_time, end_time)
+        Ok((start_time, end_time))
+    }
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum TwapType {
+    #[default]
+    Arithmetic,
+    Geometric,
+}
+
+#[cw_serde]
+pub struct TwapParams {
+    pub twap_type: TwapType,
+    pub window_seconds: u64,
+}
+
+impl Default for TwapParams {
+    fn default() -> Self {
+        Self {
+            twap_type: TwapType::default(),
+            window_seconds: DEFAULT_TWAP_WINDOW,
+        }
     }
 }
 
 #[cw_serde]
 pub struct OsmosisSwapMsg {
-    pub token_out_min_amount: String,
+    /// Fixed minimum output, mutually exclusive with `twap_slippage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_out_min_amount: Option<String>,
+    pub path: Vec<SwapAmountInRoute>,
+    /// Splits the input across several weighted paths, `path` must be empty in that case.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split_routes: Vec<WeightedRoute>,
+    /// Derives the minimum output from the TWAP at execution time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twap_slippage: Option<TwapSlippage>,
+}
+
+#[cw_serde]
+pub struct TwapSlippage {
+    /// Slippage tolerance in percent.
+    pub slippage: Decimal,
+    pub twap: Option<TwapParams>,
+}
+
+#[cw_serde]
+pub struct WeightedRoute {
+    pub weight: u64,
+    pub path: Vec<SwapAmountInRoute>,
+}
+
+#[cw_serde]
+pub struct OsmosisSwapExactAmountOutMsg {
+    pub token_out_amount: String,
+    pub token_in_max_amount: String,
     pub path: Vec<SwapAmountInRoute>,
 }
 
 #[cw_serde]
 pub struct OsmosisSwapReply {
     pub output_coin: cosmwasm_std::Coin,
+    pub refund_coin: Option<cosmwasm_std::Coin>,
 }
 
 #[cw_serde]
 pub struct OsmosisSimulateSwapResponse {
+    /// Minimum output after the slippage deduction.
+    pub output_coin: cosmwasm_std::Coin,
+    pub hops: Vec<TwapSwapHop>,
+    pub slippage_deduction: cosmwasm_std::Coin,
+}
+
+#[cw_serde]
+pub struct OsmosisBestRouteResponse {
+    pub path: Vec<SwapAmountInRoute>,
     pub output_coin: cosmwasm_std::Coin,
 }
+
+#[cw_serde]
+pub struct TwapSwapHop {
+    pub pool_id: u64,
+    pub input_denom: String,
+    pub output_denom: String,
+    pub twap_price: Decimal,
+    /// Running output amount of the path after this hop, before slippage.
+    pub amount_out: Uint128,
+}
@@ -0,0 +1,60 @@
+//! Poolmanager messages which are not available in the used osmosis-std version yet.
+
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_std_derive::CosmwasmExt;
+
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    ::prost::Message,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    CosmwasmExt,
+)]
+#[proto_message(type_url = "/osmosis.poolmanager.v1beta1.SwapAmountInSplitRoute")]
+pub struct SwapAmountInSplitRoute {
+    #[prost(message, repeated, tag = "1")]
+    pub pools: Vec<SwapAmountInRoute>,
+    #[prost(string, tag = "2")]
+    pub token_in_amount: String,
+}
+
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    ::prost::Message,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    CosmwasmExt,
+)]
+#[proto_message(type_url = "/osmosis.poolmanager.v1beta1.MsgSplitRouteSwapExactAmountIn")]
+pub struct MsgSplitRouteSwapExactAmountIn {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(message, repeated, tag = "2")]
+    pub routes: Vec<SwapAmountInSplitRoute>,
+    #[prost(string, tag = "3")]
+    pub token_in_denom: String,
+    #[prost(string, tag = "4")]
+    pub token_out_min_amount: String,
+}
+
+#[derive(
+    Clone,
+    PartialEq,
+    Eq,
+    ::prost::Message,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    CosmwasmExt,
+)]
+#[proto_message(type_url = "/osmosis.poolmanager.v1beta1.MsgSplitRouteSwapExactAmountInResponse")]
+pub struct MsgSplitRouteSwapExactAmountInResponse {
+    #[prost(string, tag = "1")]
+    pub token_out_amount: String,
+}
@@ -1,20 +1,38 @@
 use std::str::FromStr;
 
 use cosmwasm_std::{
-    Coin, CosmosMsg, Decimal, Deps, Env, Reply, Storage, SubMsgResponse, SubMsgResult, Uint128,
+    Coin, CosmosMsg, Decimal, Deps, Env, QuerierWrapper, Reply, Storage, SubMsgResponse,
+    SubMsgResult, Uint128,
 };
-use osmosis_std::types::osmosis::poolmanager::v1beta1::{
-    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+use osmosis_std::types::osmosis::{
+    gamm::v1beta1::GammQuerier,
+    poolmanager::v1beta1::{
+        MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, MsgSwapExactAmountOut,
+        MsgSwapExactAmountOutResponse, PoolmanagerQuerier, SwapAmountInRoute,
+    },
 };
 
 use crate::{
     error::OsmosisRouterError,
-    state::{has_processing_swap, load_processing_swap, store_processing_swap, ProcessingSwap},
-    OsmosisPath, OsmosisSimulateSwapResponse, OsmosisSwapMsg, OsmosisSwapReply,
+    poolmanager::{
+        MsgSplitRouteSwapExactAmountIn, MsgSplitRouteSwapExactAmountInResponse,
+        SwapAmountInSplitRoute,
+    },
+    state::{
+        has_processing_swap, load_processing_swap, store_processing_swap, ProcessingSwap, SwapType,
+    },
+    OsmosisBestRouteResponse, OsmosisPath, OsmosisSimulateSwapResponse,
+    OsmosisSwapExactAmountOutMsg, OsmosisSwapMsg, OsmosisSwapReply, TwapParams, TwapSlippage,
 };
 
+const MAX_ROUTE_SEARCH_HOPS: u32 = 4;
+// every path found is estimated with a separate query, and their number grows quickly with the
+// number of pools
+const MAX_ROUTE_SEARCH_POOLS: usize = 8;
+
 pub fn build_swap_msg(
     storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
     env: &Env,
     input_coin: Coin,
     msg: OsmosisSwapMsg,
@@ -23,13 +41,30 @@ pub fn build_swap_msg(
         return Err(OsmosisRouterError::SwapIsAlreadyInProcess {});
     }
 
+    if !msg.split_routes.is_empty() {
+        if !msg.path.is_empty() {
+            return Err(OsmosisRouterError::InvalidPath {});
+        }
+
+        return build_split_route_swap_msg(storage, querier, env, input_coin, msg);
+    }
+
     let pool_path = OsmosisPath(msg.path);
-    pool_path.validate_path(&input_coin.denom)?;
+    pool_path.validate_path(&input_coin.denom, None)?;
+
+    let token_out_min_amount = get_token_out_min_amount(
+        querier,
+        env,
+        [(&pool_path, &input_coin)],
+        msg.token_out_min_amount,
+        msg.twap_slippage,
+    )?;
 
     store_processing_swap(
         storage,
         &ProcessingSwap {
             output_denom: pool_path.get_path_output_denom(),
+            swap_type: SwapType::ExactAmountIn {},
         },
     )?;
 
@@ -37,7 +72,174 @@ pub fn build_swap_msg(
         sender: env.contract.address.to_string(),
         routes: pool_path.0,
         token_in: Some(input_coin.into()),
-        token_out_min_amount: msg.token_out_min_amount,
+        token_out_min_amount,
+    };
+
+    Ok(swap_msg.into())
+}
+
+fn build_split_route_swap_msg(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    input_coin: Coin,
+    msg: OsmosisSwapMsg,
+) -> Result<CosmosMsg, OsmosisRouterError> {
+    let weights: Vec<u64> = msg.split_routes.iter().map(|r| r.weight).collect();
+    let amounts = split_amount(input_coin.amount, &weights)?;
+
+    let output_denom = msg.split_routes[0]
+        .path
+        .last()
+        .map(|step| step.token_out_denom.clone())
+        .ok_or(OsmosisRouterError::InvalidPath {})?;
+
+    let mut routes = Vec::with_capacity(msg.split_routes.len());
+    for (route, amount) in msg.split_routes.into_iter().zip(amounts) {
+        let pool_path = OsmosisPath(route.path);
+        pool_path.validate_path(&input_coin.denom, Some(&output_denom))?;
+
+        let route_input_coin = Coin {
+            denom: input_coin.denom.clone(),
+            amount,
+        };
+        routes.push((pool_path, route_input_coin));
+    }
+
+    let token_out_min_amount = get_token_out_min_amount(
+        querier,
+        env,
+        routes.iter().map(|(path, coin)| (path, coin)),
+        msg.token_out_min_amount,
+        msg.twap_slippage,
+    )?;
+
+    store_processing_swap(
+        storage,
+        &ProcessingSwap {
+            output_denom,
+            swap_type: SwapType::SplitRouteExactAmountIn {},
+        },
+    )?;
+
+    let swap_msg = MsgSplitRouteSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes: routes
+            .into_iter()
+            .map(|(path, coin)| SwapAmountInSplitRoute {
+                pools: path.0,
+                token_in_amount: coin.amount.to_string(),
+            })
+            .collect(),
+        token_in_denom: input_coin.denom,
+        token_out_min_amount,
+    };
+
+    Ok(swap_msg.into())
+}
+
+/// Returns either the fixed minimum output or the one derived from the current
+/// TWAP of every route minus the given slippage.
+fn get_token_out_min_amount<'a>(
+    querier: &QuerierWrapper,
+    env: &Env,
+    routes: impl IntoIterator<Item = (&'a OsmosisPath, &'a Coin)>,
+    token_out_min_amount: Option<String>,
+    twap_slippage: Option<TwapSlippage>,
+) -> Result<String, OsmosisRouterError> {
+    match (token_out_min_amount, twap_slippage) {
+        (Some(token_out_min_amount), None) => Ok(token_out_min_amount),
+        (None, Some(twap_slippage)) => {
+            let twap = twap_slippage.twap.unwrap_or_default();
+
+            let mut token_out_min_amount = Uint128::zero();
+            for (pool_path, input_coin) in routes {
+                let estimate = pool_path.calculate_twap_output_amount(
+                    querier,
+                    input_coin,
+                    twap_slippage.slippage,
+                    &env.block.time,
+                    &twap,
+                )?;
+                token_out_min_amount =
+                    token_out_min_amount.checked_add(estimate.output_coin.amount)?;
+            }
+
+            Ok(token_out_min_amount.to_string())
+        }
+        _ => Err(OsmosisRouterError::InvalidMinOutput {}),
+    }
+}
+
+/// Splits `amount` proportionally to `weights`. Rounding dust goes to the last route.
+fn split_amount(amount: Uint128, weights: &[u64]) -> Result<Vec<Uint128>, OsmosisRouterError> {
+    let total_weight = weights
+        .iter()
+        .try_fold(0u64, |total, weight| total.checked_add(*weight))
+        .ok_or(OsmosisRouterError::InvalidSplitRoutes {})?;
+    if total_weight == 0 || weights.contains(&0) {
+        return Err(OsmosisRouterError::InvalidSplitRoutes {});
+    }
+
+    let mut amounts: Vec<Uint128> = weights
+        .iter()
+        .map(|weight| amount.multiply_ratio(*weight, total_weight))
+        .collect();
+
+    let distributed = amounts.iter().sum::<Uint128>();
+    if let Some(last) = amounts.last_mut() {
+        *last += amount.checked_sub(distributed)?;
+    }
+
+    if amounts.iter().any(|a| a.is_zero()) {
+        return Err(OsmosisRouterError::InvalidSplitRoutes {});
+    }
+
+    Ok(amounts)
+}
+
+pub fn build_swap_exact_amount_out_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    input_coin: Coin,
+    msg: OsmosisSwapExactAmountOutMsg,
+) -> Result<CosmosMsg, OsmosisRouterError> {
+    if has_processing_swap(storage)? {
+        return Err(OsmosisRouterError::SwapIsAlreadyInProcess {});
+    }
+
+    let token_in_max_amount = Uint128::from_str(&msg.token_in_max_amount)?;
+    if token_in_max_amount > input_coin.amount {
+        return Err(OsmosisRouterError::InvalidTokenInMaxAmount {
+            max_amount: token_in_max_amount.to_string(),
+            amount: input_coin.amount.to_string(),
+        });
+    }
+
+    let pool_path = OsmosisPath(msg.path);
+    pool_path.validate_path(&input_coin.denom, None)?;
+
+    let token_out = Coin {
+        denom: pool_path.get_path_output_denom(),
+        amount: Uint128::from_str(&msg.token_out_amount)?,
+    };
+
+    store_processing_swap(
+        storage,
+        &ProcessingSwap {
+            output_denom: token_out.denom.clone(),
+            swap_type: SwapType::ExactAmountOut {
+                input_coin: input_coin.clone(),
+                token_out_amount: token_out.amount,
+            },
+        },
+    )?;
+
+    let swap_msg = MsgSwapExactAmountOut {
+        sender: env.contract.address.to_string(),
+        routes: pool_path.to_amount_out_routes(&input_coin.denom),
+        token_in_max_amount: token_in_max_amount.to_string(),
+        token_out: Some(token_out.into()),
     };
 
     Ok(swap_msg.into())
@@ -48,15 +250,56 @@ pub fn get_swap_amount_out_response(
     msg: Reply,
 ) -> Result<OsmosisSwapReply, OsmosisRouterError> {
     if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
-        let res: MsgSwapExactAmountInResponse = b.try_into().map_err(OsmosisRouterError::Std)?;
         let processing_swap = load_processing_swap(storage)?;
 
-        return Ok(OsmosisSwapReply {
-            output_coin: Coin {
-                denom: processing_swap.output_denom,
-                amount: Uint128::from_str(&res.token_out_amount)?,
-            },
-        });
+        return match processing_swap.swap_type {
+            SwapType::ExactAmountIn {} => {
+                let res: MsgSwapExactAmountInResponse =
+                    b.try_into().map_err(OsmosisRouterError::Std)?;
+
+                Ok(OsmosisSwapReply {
+                    output_coin: Coin {
+                        denom: processing_swap.output_denom,
+                        amount: Uint128::from_str(&res.token_out_amount)?,
+                    },
+                    refund_coin: None,
+                })
+            }
+            SwapType::SplitRouteExactAmountIn {} => {
+                let res: MsgSplitRouteSwapExactAmountInResponse =
+                    b.try_into().map_err(OsmosisRouterError::Std)?;
+
+                Ok(OsmosisSwapReply {
+                    output_coin: Coin {
+                        denom: processing_swap.output_denom,
+                        amount: Uint128::from_str(&res.token_out_amount)?,
+                    },
+                    refund_coin: None,
+                })
+            }
+            SwapType::ExactAmountOut {
+                input_coin,
+                token_out_amount,
+            } => {
+                let res: MsgSwapExactAmountOutResponse =
+                    b.try_into().map_err(OsmosisRouterError::Std)?;
+
+                // return unused part of the input back to the user
+                let token_in_amount = Uint128::from_str(&res.token_in_amount)?;
+                let refund_amount = input_coin.amount.checked_sub(token_in_amount)?;
+
+                Ok(OsmosisSwapReply {
+                    output_coin: Coin {
+                        denom: processing_swap.output_denom,
+                        amount: token_out_amount,
+                    },
+                    refund_coin: (!refund_amount.is_zero()).then_some(Coin {
+                        denom: input_coin.denom,
+                        amount: refund_amount,
+                    }),
+                })
+            }
+        };
     }
 
     Err(OsmosisRouterError::FailedSwap {
@@ -70,16 +313,310 @@ pub fn estimate_min_twap_output(
     input_coin: Coin,
     path: Vec<SwapAmountInRoute>,
     slippage: Decimal,
+    twap: TwapParams,
 ) -> Result<OsmosisSimulateSwapResponse, OsmosisRouterError> {
     let pool_path = OsmosisPath(path);
-    pool_path.validate_path(&input_coin.denom)?;
+    pool_path.validate_path(&input_coin.denom, None)?;
 
-    let output_coin = pool_path.calculate_twap_output_amount(
+    pool_path.calculate_twap_output_amount(
         &deps.querier,
         &input_coin,
         slippage,
         &env.block.time,
-    )?;
+        &twap,
+    )
+}
+
+pub fn find_best_route(
+    deps: Deps,
+    input_coin: Coin,
+    output_denom: String,
+    mut pool_ids: Vec<u64>,
+    max_hops: u32,
+) -> Result<OsmosisBestRouteResponse, OsmosisRouterError> {
+    if max_hops == 0 || max_hops > MAX_ROUTE_SEARCH_HOPS {
+        return Err(OsmosisRouterError::InvalidMaxHops {
+            max: MAX_ROUTE_SEARCH_HOPS,
+        });
+    }
+
+    pool_ids.sort_unstable();
+    pool_ids.dedup();
+    if pool_ids.len() > MAX_ROUTE_SEARCH_POOLS {
+        return Err(OsmosisRouterError::TooManyPools {
+            max: MAX_ROUTE_SEARCH_POOLS,
+        });
+    }
+
+    // pools without gamm liquidity, like concentrated liquidity pools, are skipped
+    let gamm_querier = GammQuerier::new(&deps.querier);
+    let pools: Vec<(u64, Vec<String>)> = pool_ids
+        .into_iter()
+        .filter_map(|pool_id| {
+            let liquidity = gamm_querier.total_pool_liquidity(pool_id).ok()?.liquidity;
+            Some((pool_id, liquidity.into_iter().map(|c| c.denom).collect()))
+        })
+        .collect();
+
+    let mut paths = vec![];
+    collect_paths(
+        &pools,
+        &output_denom,
+        max_hops,
+        &mut vec![input_coin.denom.clone()],
+        &mut vec![],
+        &mut paths,
+    );
+
+    let poolmanager_querier = PoolmanagerQuerier::new(&deps.querier);
+    let mut best_route: Option<OsmosisBestRouteResponse> = None;
+
+    for path in paths {
+        // paths without enough liquidity fail to estimate and are skipped
+        let Ok(estimate) = poolmanager_querier.estimate_swap_exact_amount_in(
+            path[0].pool_id,
+            input_coin.to_string(),
+            path.clone(),
+        ) else {
+            continue;
+        };
+        let amount = Uint128::from_str(&estimate.token_out_amount)?;
+
+        let is_better = match &best_route {
+            Some(best) => amount > best.output_coin.amount,
+            None => true,
+        };
+
+        if is_better {
+            best_route = Some(OsmosisBestRouteResponse {
+                path,
+                output_coin: Coin {
+                    denom: output_denom.clone(),
+                    amount,
+                },
+            });
+        }
+    }
+
+    best_route.ok_or(OsmosisRouterError::RouteNotFound {})
+}
+
+/// Depth first search of every path from the last seen denom to `output_denom`
+/// which uses each pool and each denom at most once.
+fn collect_paths(
+    pools: &[(u64, Vec<String>)],
+    output_denom: &str,
+    hops_left: u32,
+    seen_denoms: &mut Vec<String>,
+    path: &mut Vec<SwapAmountInRoute>,
+    paths: &mut Vec<Vec<SwapAmountInRoute>>,
+) {
+    let current_denom = seen_denoms.last().cloned().unwrap_or_default();
+
+    for (pool_id, denoms) in pools.iter() {
+        if !denoms.contains(&current_denom) || path.iter().any(|step| step.pool_id == *pool_id) {
+            continue;
+        }
+
+        for denom in denoms.iter() {
+            if seen_denoms.contains(denom) {
+                continue;
+            }
+
+            path.push(SwapAmountInRoute {
+                pool_id: *pool_id,
+                token_out_denom: denom.to_owned(),
+            });
+
+            if denom == output_denom {
+                paths.push(path.clone());
+            } else if hops_left > 1 {
+                seen_denoms.push(denom.to_owned());
+                collect_paths(pools, output_denom, hops_left - 1, seen_denoms, path, paths);
+                seen_denoms.pop();
+            }
+
+            path.pop();
+        }
+    }
+}
 
-    Ok(OsmosisSimulateSwapResponse { output_coin })
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+
+    use super::*;
+
+    /// Dispatches an exact amount out swap of up to 100 uosmo for 50 uatom and replies with the
+    /// input the pool used.
+    fn exact_amount_out_reply(token_in_amount: &str) -> OsmosisSwapReply {
+        let mut storage = MockStorage::new();
+        build_swap_exact_amount_out_msg(
+            &mut storage,
+            &mock_env(),
+            Coin::new(100, "uosmo"),
+            OsmosisSwapExactAmountOutMsg {
+                token_out_amount: "50".to_owned(),
+                token_in_max_amount: "100".to_owned(),
+                path: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: "uatom".to_owned(),
+                }],
+            },
+        )
+        .unwrap();
+
+        let response = MsgSwapExactAmountOutResponse {
+            token_in_amount: token_in_amount.to_owned(),
+        };
+        let reply = Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(response.into()),
+            }),
+        };
+
+        get_swap_amount_out_response(&mut storage, reply).unwrap()
+    }
+
+    #[test]
+    fn exact_amount_out_reply_refunds_unused_input() {
+        assert_eq!(
+            exact_amount_out_reply("80"),
+            OsmosisSwapReply {
+                output_coin: Coin::new(50, "uatom"),
+                refund_coin: Some(Coin::new(20, "uosmo")),
+            }
+        );
+    }
+
+    #[test]
+    fn exact_amount_out_reply_without_unused_input_has_no_refund() {
+        assert_eq!(
+            exact_amount_out_reply("100"),
+            OsmosisSwapReply {
+                output_coin: Coin::new(50, "uatom"),
+                refund_coin: None,
+            }
+        );
+    }
+
+    fn amounts(amounts: &[u128]) -> Vec<Uint128> {
+        amounts.iter().map(|amount| Uint128::new(*amount)).collect()
+    }
+
+    #[test]
+    fn split_amount_gives_dust_to_last_route() {
+        assert_eq!(
+            split_amount(Uint128::new(100), &[1, 1, 1]).unwrap(),
+            amounts(&[33, 33, 34])
+        );
+        assert_eq!(
+            split_amount(Uint128::new(1001), &[3, 7]).unwrap(),
+            amounts(&[300, 701])
+        );
+    }
+
+    #[test]
+    fn split_amount_rejects_zero_weights() {
+        for weights in [&[][..], &[0], &[1, 0]] {
+            let err = split_amount(Uint128::new(100), weights).unwrap_err();
+            assert!(matches!(err, OsmosisRouterError::InvalidSplitRoutes {}));
+        }
+    }
+
+    #[test]
+    fn split_amount_rejects_empty_routes() {
+        for (amount, weights) in [(0, &[1, 1][..]), (1, &[1, 1]), (10, &[1, 1_000])] {
+            let err = split_amount(Uint128::new(amount), weights).unwrap_err();
+            assert!(matches!(err, OsmosisRouterError::InvalidSplitRoutes {}));
+        }
+    }
+
+    #[test]
+    fn split_amount_rejects_overflowing_weights() {
+        let err = split_amount(Uint128::new(100), &[u64::MAX, 1]).unwrap_err();
+        assert!(matches!(err, OsmosisRouterError::InvalidSplitRoutes {}));
+    }
+
+    /// Paths from uosmo to `output_denom` as (pool id, output denom) steps.
+    fn paths(
+        pools: &[(u64, &[&str])],
+        output_denom: &str,
+        max_hops: u32,
+    ) -> Vec<Vec<(u64, String)>> {
+        let pools: Vec<(u64, Vec<String>)> = pools
+            .iter()
+            .map(|(pool_id, denoms)| (*pool_id, denoms.iter().map(|d| d.to_string()).collect()))
+            .collect();
+
+        let mut paths = vec![];
+        collect_paths(
+            &pools,
+            output_denom,
+            max_hops,
+            &mut vec!["uosmo".to_owned()],
+            &mut vec![],
+            &mut paths,
+        );
+
+        paths
+            .into_iter()
+            .map(|path| {
+                path.into_iter()
+                    .map(|step| (step.pool_id, step.token_out_denom))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn path(steps: &[(u64, &str)]) -> Vec<(u64, String)> {
+        steps
+            .iter()
+            .map(|(pool_id, denom)| (*pool_id, denom.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn collect_paths_respects_hop_limit() {
+        let pools: &[(u64, &[&str])] = &[
+            (1, &["uosmo", "uatom"]),
+            (2, &["uatom", "uion"]),
+            (3, &["uion", "uusdc"]),
+            (4, &["uosmo", "uion"]),
+        ];
+
+        assert_eq!(paths(pools, "uusdc", 1), Vec::<Vec<(u64, String)>>::new());
+        assert_eq!(
+            paths(pools, "uusdc", 2),
+            vec![path(&[(4, "uion"), (3, "uusdc")])]
+        );
+        assert_eq!(
+            paths(pools, "uusdc", 3),
+            vec![
+                path(&[(1, "uatom"), (2, "uion"), (3, "uusdc")]),
+                path(&[(4, "uion"), (3, "uusdc")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_paths_uses_pools_once() {
+        let pools: &[(u64, &[&str])] = &[(1, &["uosmo", "uatom", "uusdc"])];
+
+        assert_eq!(paths(pools, "uusdc", 3), vec![path(&[(1, "uusdc")])]);
+    }
+
+    #[test]
+    fn collect_paths_visits_denoms_once() {
+        let pools: &[(u64, &[&str])] = &[
+            (1, &["uosmo", "uatom"]),
+            (2, &["uatom", "uosmo"]),
+            (3, &["uosmo", "uusdc"]),
+        ];
+
+        // going back to uosmo through pool 2 would give uosmo -> uatom -> uosmo -> uusdc
+        assert_eq!(paths(pools, "uusdc", 4), vec![path(&[(3, "uusdc")])]);
+    }
 }
@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{StdResult, Storage};
+use cosmwasm_std::{Coin, StdResult, Storage, Uint128};
 use cw_storage_plus::Item;
 
 const PROCESSING_SWAP: Item<ProcessingSwap> = Item::new("processing_swap");
@@ -7,6 +7,17 @@ const PROCESSING_SWAP: Item<ProcessingSwap> = Item::new("processing_swap");
 #[cw_serde]
 pub struct ProcessingSwap {
     pub output_denom: String,
+    pub swap_type: SwapType,
+}
+
+#[cw_serde]
+pub enum SwapType {
+    ExactAmountIn {},
+    SplitRouteExactAmountIn {},
+    ExactAmountOut {
+        input_coin: Coin,
+        token_out_amount: Uint128,
+    },
 }
 
 pub(crate) fn has_processing_swap(storage: &mut dyn Storage) -> StdResult<bool> {
@@ -20,6 +31,11 @@ pub(crate) fn store_processing_swap(
     PROCESSING_SWAP.save(storage, data)
 }
 
+/// Drops the state of a swap whose message failed without reverting the transaction.
+pub fn remove_processing_swap(storage: &mut dyn Storage) {
+    PROCESSING_SWAP.remove(storage)
+}
+
 pub(crate) fn load_processing_swap(storage: &mut dyn Storage) -> StdResult<ProcessingSwap> {
     let data = PROCESSING_SWAP.load(storage)?;
     PROCESSING_SWAP.remove(storage);